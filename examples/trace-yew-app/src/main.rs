@@ -3,6 +3,7 @@ use tracing_subscriber::{
     fmt::format::{FmtSpan, Pretty},
     prelude::*,
 };
+use wasm_bindgen::{closure::Closure, JsCast};
 use yew::{function_component, html, Html};
 
 #[function_component]
@@ -21,13 +22,24 @@ fn main() {
         .with_writer(tracing_web::MakeWebConsoleWriter::new().with_pretty_level())
         .with_level(false)
         .with_span_events(FmtSpan::ACTIVE);
-    let perf_layer = tracing_web::performance_layer().with_details_from_fields(Pretty::default());
+    let perf_layer = tracing_web::performance_layer()
+        .with_details_from_fields(Pretty::default())
+        .with_open_span_tracking();
+    let open_spans = perf_layer.open_spans().unwrap();
 
     tracing_subscriber::registry()
         .with(fmt_layer)
         .with(perf_layer)
         .init();
 
+    // Measure spans that are still entered when the page is left
+    let on_unload = Closure::<dyn Fn()>::new(move || open_spans.flush());
+    web_sys::window()
+        .unwrap()
+        .add_event_listener_with_callback("beforeunload", on_unload.as_ref().unchecked_ref())
+        .unwrap();
+    on_unload.forget();
+
     tracing::debug_span!("top-level", i = 5).in_scope(|| {
         tracing::trace!("This is a trace message.");
         let message = "debug message";
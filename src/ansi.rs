@@ -0,0 +1,126 @@
+// A converter of the ANSI escape codes emitted by `tracing-subscriber` into console CSS styles.
+//
+// This is not a general ANSI parser. Only SGR ("select graphic rendition") sequences with the common attributes and
+// 8/16 colors are translated, all other escape sequences are dropped.
+
+const COLORS: [&str; 8] = [
+    "#2E3436", "#CC0000", "#4E9A06", "#C4A000", "#3465A4", "#75507B", "#06989A", "#D3D7CF",
+];
+const BRIGHT_COLORS: [&str; 8] = [
+    "#555753", "#EF2929", "#8AE234", "#FCE94F", "#729FCF", "#AD7FA8", "#34E2E2", "#EEEEEC",
+];
+
+#[derive(Default)]
+struct SgrState {
+    bold: bool,
+    dimmed: bool,
+    italic: bool,
+    underline: bool,
+    strikethrough: bool,
+    foreground: Option<&'static str>,
+    background: Option<&'static str>,
+}
+
+impl SgrState {
+    fn apply(&mut self, params: &str) {
+        // An empty parameter, e.g. in `ESC[m`, is equivalent to 0, i.e. a reset
+        let mut params = params
+            .split(';')
+            .map(|param| param.parse::<u8>().unwrap_or(0));
+        while let Some(param) = params.next() {
+            match param {
+                0 => *self = Self::default(),
+                1 => self.bold = true,
+                2 => self.dimmed = true,
+                3 => self.italic = true,
+                4 => self.underline = true,
+                9 => self.strikethrough = true,
+                22 => {
+                    self.bold = false;
+                    self.dimmed = false;
+                }
+                23 => self.italic = false,
+                24 => self.underline = false,
+                29 => self.strikethrough = false,
+                30..=37 => self.foreground = Some(COLORS[(param - 30) as usize]),
+                39 => self.foreground = None,
+                40..=47 => self.background = Some(COLORS[(param - 40) as usize]),
+                49 => self.background = None,
+                90..=97 => self.foreground = Some(BRIGHT_COLORS[(param - 90) as usize]),
+                100..=107 => self.background = Some(BRIGHT_COLORS[(param - 100) as usize]),
+                // Extended colors are not emitted by tracing-subscriber, skip over their arguments
+                38 | 48 => match params.next() {
+                    Some(5) => {
+                        params.next();
+                    }
+                    Some(2) => {
+                        params.nth(2);
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+    }
+    fn css(&self) -> String {
+        let mut css = String::new();
+        if let Some(color) = self.foreground {
+            css.push_str("color: ");
+            css.push_str(color);
+            css.push(';');
+        }
+        if let Some(color) = self.background {
+            css.push_str("background: ");
+            css.push_str(color);
+            css.push(';');
+        }
+        if self.bold {
+            css.push_str("font-weight: bold;");
+        }
+        if self.dimmed {
+            css.push_str("opacity: 0.7;");
+        }
+        if self.italic {
+            css.push_str("font-style: italic;");
+        }
+        match (self.underline, self.strikethrough) {
+            (true, true) => css.push_str("text-decoration: underline line-through;"),
+            (true, false) => css.push_str("text-decoration: underline;"),
+            (false, true) => css.push_str("text-decoration: line-through;"),
+            (false, false) => {}
+        }
+        css
+    }
+}
+
+/// Split text containing ANSI escape codes into segments of plain text, with the equivalent CSS style of each.
+pub(crate) fn ansi_to_css(text: &str) -> Vec<(String, String)> {
+    let mut segments = vec![];
+    let mut state = SgrState::default();
+    let mut rest = text;
+    while let Some(start) = rest.find('\x1b') {
+        if start > 0 {
+            segments.push((rest[..start].to_owned(), state.css()));
+        }
+        let sequence = &rest[start + 1..];
+        if let Some(csi) = sequence.strip_prefix('[') {
+            // Parameters are followed by a single final byte in the range 0x40..=0x7E
+            match csi.find(|c: char| ('\x40'..='\x7e').contains(&c)) {
+                Some(end) => {
+                    if csi[end..].starts_with('m') {
+                        state.apply(&csi[..end]);
+                    }
+                    rest = &csi[end + 1..];
+                }
+                None => rest = "",
+            }
+        } else {
+            // Not a control sequence, drop the lone escape character
+            rest = sequence;
+        }
+    }
+    if !rest.is_empty() {
+        segments.push((rest.to_owned(), state.css()));
+    }
+    segments
+}
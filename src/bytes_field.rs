@@ -0,0 +1,105 @@
+use std::{cell::RefCell, fmt::Write};
+
+thread_local! {
+    // Values passed to `bytes_field`, waiting to be logged by the next console line on this thread.
+    static STASHED: RefCell<Vec<(&'static str, Vec<u8>)>> = RefCell::new(Vec::new());
+}
+
+/// The encoding of byte buffers passed with [`bytes_field`], see [`MakeWebConsoleWriter::with_bytes_format`].
+///
+/// [`MakeWebConsoleWriter::with_bytes_format`]: crate::MakeWebConsoleWriter::with_bytes_format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub enum BytesFormat {
+    /// Lowercase hexadecimal, two digits per byte.
+    #[default]
+    Hex,
+    /// Standard base64, with padding.
+    Base64,
+}
+
+/// Pass a byte buffer along with an event, to be logged in a readable encoding in the console.
+///
+/// Tracing fields can not hold bytes natively, and their debug representation is hard to read. Instead, use this
+/// helper as the value of a field. It sets the bytes aside, and returns a placeholder to record in its place. The
+/// next line written by a [`ConsoleWriter`] on the same thread then appends them as `name=<encoded>`, encoded as
+/// configured with [`MakeWebConsoleWriter::with_bytes_format`].
+///
+/// ```rust, ignore
+/// tracing::debug!(payload = tracing_web::bytes_field("payload", &buffer), "received");
+/// ```
+///
+/// The same constraints as for [`js_field`](crate::js_field) apply.
+///
+/// [`ConsoleWriter`]: crate::ConsoleWriter
+/// [`MakeWebConsoleWriter::with_bytes_format`]: crate::MakeWebConsoleWriter::with_bytes_format
+pub fn bytes_field(name: &'static str, bytes: &[u8]) -> &'static str {
+    STASHED.with(|stashed| stashed.borrow_mut().push((name, bytes.to_vec())));
+    "[bytes]"
+}
+
+/// Take the values set aside by [`bytes_field`] since the last call.
+pub(crate) fn take_bytes_fields() -> Vec<(&'static str, Vec<u8>)> {
+    STASHED.with(|stashed| std::mem::take(&mut *stashed.borrow_mut()))
+}
+
+impl BytesFormat {
+    pub(crate) fn encode(self, bytes: &[u8]) -> String {
+        match self {
+            BytesFormat::Hex => {
+                let mut encoded = String::with_capacity(bytes.len() * 2);
+                for byte in bytes {
+                    let _ = write!(encoded, "{byte:02x}");
+                }
+                encoded
+            }
+            BytesFormat::Base64 => encode_base64(bytes),
+        }
+    }
+}
+
+fn encode_base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = ((b[0] as u32) << 16) | ((b[1] as u32) << 8) | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BytesFormat;
+
+    #[test]
+    fn hex() {
+        assert_eq!(BytesFormat::Hex.encode(b""), "");
+        assert_eq!(
+            BytesFormat::Hex.encode(&[0x00, 0x0f, 0xab, 0xff]),
+            "000fabff"
+        );
+    }
+
+    #[test]
+    fn base64_padding() {
+        assert_eq!(BytesFormat::Base64.encode(b""), "");
+        assert_eq!(BytesFormat::Base64.encode(b"f"), "Zg==");
+        assert_eq!(BytesFormat::Base64.encode(b"fo"), "Zm8=");
+        assert_eq!(BytesFormat::Base64.encode(b"foo"), "Zm9v");
+        assert_eq!(BytesFormat::Base64.encode(b"foob"), "Zm9vYg==");
+        assert_eq!(BytesFormat::Base64.encode(&[0xfb, 0xff]), "+/8=");
+    }
+}
@@ -0,0 +1,275 @@
+use std::{
+    cell::Cell,
+    fmt::{self, Write},
+};
+
+use js_sys::{JsString, Object, Reflect};
+use tracing_core::{
+    field::{Field, Visit},
+    Event, Subscriber,
+};
+use tracing_subscriber::{
+    fmt::{format, FmtContext, FormatEvent, FormatFields},
+    registry::LookupSpan,
+};
+use wasm_bindgen::JsValue;
+
+use crate::js_field::js_field;
+
+/// A compact, single-line [`FormatEvent`] tuned for the console.
+///
+/// Events are formatted as `LEVEL target: message {field=value ...}`, without the alignment of the formatters in
+/// [`mod@tracing_subscriber::fmt::format`], which is designed for terminals and looks odd in the dev-tools.
+///
+/// ```rust, no_run
+/// use tracing_web::{CompactConsoleFormat, MakeWebConsoleWriter};
+///
+/// let fmt_layer = tracing_subscriber::fmt::layer()
+///     .with_ansi(false)
+///     .without_time()
+///     .event_format(CompactConsoleFormat::new())
+///     .with_writer(MakeWebConsoleWriter::new());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CompactConsoleFormat {
+    fields_object: bool,
+    sorted: bool,
+    highlighted_keys: bool,
+    max_fields: Option<usize>,
+    quoted_strings: bool,
+}
+
+impl CompactConsoleFormat {
+    /// Create a new compact format.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Log the fields of events without a message as an inspectable object, instead of as text.
+    ///
+    /// Events such as `tracing::info!(x = 1)` are then shown as `INFO target: fields=Object`, where the object can be
+    /// expanded in the console. The object is passed along like a [`js_field`], so this requires a
+    /// [`ConsoleWriter`](crate::ConsoleWriter) on the same thread. Events with a message are formatted as usual.
+    pub fn with_fields_only_as_object(mut self) -> Self {
+        self.fields_object = true;
+        self
+    }
+    /// Write the fields sorted alphabetically by name, instead of in the order they are recorded.
+    ///
+    /// This makes the output deterministic, e.g. to diff logs across runs. See also [`SortedFields`] to sort the
+    /// fields of spans.
+    ///
+    /// [`SortedFields`]: crate::SortedFields
+    pub fn with_sorted_fields(mut self) -> Self {
+        self.sorted = true;
+        self
+    }
+    /// Show the names of fields in a muted style, to tell them apart from the values at a glance.
+    ///
+    /// The names are wrapped in ANSI escape codes, and the formatter flags the line for the [`ConsoleWriter`] on the
+    /// same thread. The writer then turns the codes into a separately styled segment for each name, as with
+    /// [`MakeWebConsoleWriter::with_ansi_styles`], but without that option having to be enabled. Other writers, e.g.
+    /// one writing to a buffer, get the escape codes as is.
+    ///
+    /// [`ConsoleWriter`]: crate::ConsoleWriter
+    /// [`MakeWebConsoleWriter::with_ansi_styles`]: crate::MakeWebConsoleWriter::with_ansi_styles
+    pub fn with_highlighted_keys(mut self) -> Self {
+        self.highlighted_keys = true;
+        self
+    }
+    /// Write at most `max` fields, and append the number of skipped fields as `, …(3 more)`.
+    ///
+    /// With [sorted fields](Self::with_sorted_fields), the first fields by name are written. Fields logged as an
+    /// [object](Self::with_fields_only_as_object) are not limited. See [`MaxFields`] to limit the fields of spans.
+    ///
+    /// [`MaxFields`]: crate::MaxFields
+    pub fn with_max_fields(mut self, max: usize) -> Self {
+        self.max_fields = Some(max);
+        self
+    }
+    /// Write string values in quotes, e.g. `msg="hello world"` instead of `msg=hello world`.
+    ///
+    /// This makes it unambiguous where a value ends, e.g. to parse scraped logs. Quotes and other special characters
+    /// in the values are escaped as in the [`Debug`](fmt::Debug) output of a `str`. The message is not quoted.
+    pub fn with_quoted_strings(mut self) -> Self {
+        self.quoted_strings = true;
+        self
+    }
+}
+
+// Dims the text up to the reset, shown in a muted style by the console writer
+const KEY_STYLE: &str = "\x1b[2m";
+const RESET_STYLE: &str = "\x1b[0m";
+
+thread_local! {
+    // Whether the line being written contains highlighted keys, to be styled by the console writer.
+    static STYLED_KEYS: Cell<bool> = Cell::new(false);
+}
+
+/// Take whether the line currently being written contains keys highlighted with ANSI escape codes.
+pub(crate) fn take_styled_keys() -> bool {
+    STYLED_KEYS.with(|styled| styled.replace(false))
+}
+
+// Collects the message and the remaining fields of an event separately.
+struct CompactVisitor {
+    message: String,
+    fields: String,
+    // The fields as JS values, if they might be logged as an object
+    object: Option<Object>,
+    // The formatted fields, if they are sorted once all have been recorded
+    sorted: Option<Vec<(&'static str, String)>>,
+    highlighted_keys: bool,
+    quoted_strings: bool,
+    // The number of fields that can still be written, if limited
+    remaining: Option<usize>,
+    skipped: usize,
+}
+
+impl CompactVisitor {
+    // Whether to write another field, counting it towards the limit.
+    fn admit(&mut self) -> bool {
+        match &mut self.remaining {
+            Some(0) => {
+                self.skipped += 1;
+                false
+            }
+            Some(remaining) => {
+                *remaining -= 1;
+                true
+            }
+            None => true,
+        }
+    }
+    fn record_value(&mut self, field: &Field, value: JsValue) {
+        if let Some(object) = &self.object {
+            let key = JsString::from(field.name());
+            Reflect::set(object, &key, &value).unwrap();
+        }
+    }
+    fn write_field(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if let Some(sorted) = &mut self.sorted {
+            sorted.push((field.name(), format!("{:?}", value)));
+            return;
+        }
+        if !self.admit() {
+            return;
+        }
+        self.write_name(field.name());
+        let _ = write!(self.fields, "{:?}", value);
+    }
+    fn write_name(&mut self, name: &str) {
+        if !self.fields.is_empty() {
+            self.fields.push(' ');
+        }
+        if self.highlighted_keys {
+            let _ = write!(self.fields, "{KEY_STYLE}{name}={RESET_STYLE}");
+        } else {
+            let _ = write!(self.fields, "{name}=");
+        }
+    }
+    fn finish(&mut self) {
+        if let Some(mut sorted) = self.sorted.take() {
+            sorted.sort_by_key(|&(name, _)| name);
+            for (name, value) in sorted {
+                if !self.admit() {
+                    continue;
+                }
+                self.write_name(name);
+                self.fields.push_str(&value);
+            }
+        }
+        if self.skipped > 0 {
+            let _ = write!(self.fields, ", …({} more)", self.skipped);
+        }
+    }
+}
+
+impl Visit for CompactVisitor {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.record_value(field, JsValue::from(value));
+        self.write_field(field, &value);
+    }
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.record_value(field, JsValue::from(value as f64));
+        self.write_field(field, &value);
+    }
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.record_value(field, JsValue::from(value as f64));
+        self.write_field(field, &value);
+    }
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.record_value(field, JsValue::from(value));
+        self.write_field(field, &value);
+    }
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            self.record_value(field, JsValue::from(value));
+            if self.quoted_strings {
+                self.write_field(field, &value);
+            } else {
+                self.write_field(field, &format_args!("{}", value));
+            }
+        }
+    }
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            if self.object.is_some() {
+                self.record_value(field, JsValue::from(format!("{:?}", value)));
+            }
+            self.write_field(field, value);
+        }
+    }
+}
+
+impl<S, N> FormatEvent<S, N> for CompactConsoleFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        _ctx: &FmtContext<'_, S, N>,
+        mut writer: format::Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let meta = event.metadata();
+        let mut visitor = CompactVisitor {
+            message: String::new(),
+            fields: String::new(),
+            object: self.fields_object.then(Object::new),
+            sorted: self.sorted.then(Vec::new),
+            highlighted_keys: self.highlighted_keys,
+            quoted_strings: self.quoted_strings,
+            remaining: self.max_fields,
+            skipped: 0,
+        };
+        event.record(&mut visitor);
+        visitor.finish();
+        // Always overwrite, so that a line that is not written does not affect the next one
+        let styled_keys = self.highlighted_keys && !visitor.fields.is_empty();
+        STYLED_KEYS.with(|styled| styled.set(styled_keys));
+
+        if let Some(object) = visitor.object.filter(|_| visitor.message.is_empty()) {
+            if !visitor.fields.is_empty() {
+                js_field("fields", object.into());
+            }
+            return writeln!(writer, "{} {}:", meta.level(), meta.target());
+        }
+
+        write!(
+            writer,
+            "{} {}: {}",
+            meta.level(),
+            meta.target(),
+            visitor.message
+        )?;
+        if !visitor.fields.is_empty() {
+            write!(writer, " {{{}}}", visitor.fields)?;
+        }
+        writeln!(writer)
+    }
+}
@@ -0,0 +1,226 @@
+use std::{
+    cell::{Cell, RefCell},
+    marker::PhantomData,
+};
+
+use tracing_core::{span, Event, Level, Subscriber};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+use wasm_bindgen::JsValue;
+use web_sys::console;
+
+use crate::console_writer::{LogDispatcher, LogOptions};
+
+/// A [`Layer`] that groups the console output of spans with `console.group`.
+///
+/// Each time a span is entered, a group titled with the span name is opened, which is closed again when the span
+/// exits. Output written by [`MakeWebConsoleWriter`] while the span is entered then appears nested in that group.
+///
+/// [`MakeWebConsoleWriter`]: crate::MakeWebConsoleWriter
+pub struct ConsoleGroupLayer<S> {
+    collapsed: bool,
+    expand_on_error: bool,
+    namespace: Option<String>,
+    _inner: PhantomData<fn(S)>,
+}
+
+/// Construct a new layer grouping the console output of spans.
+///
+/// By default, groups are shown expanded.
+pub fn console_group_layer<S>() -> ConsoleGroupLayer<S>
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    ConsoleGroupLayer {
+        collapsed: false,
+        expand_on_error: false,
+        namespace: None,
+        _inner: PhantomData,
+    }
+}
+
+impl<S> ConsoleGroupLayer<S> {
+    /// Choose whether groups are opened collapsed, with `console.groupCollapsed`, or expanded, with `console.group`.
+    pub fn with_collapsed(mut self, collapsed: bool) -> Self {
+        self.collapsed = collapsed;
+        self
+    }
+    /// Automatically expand collapsed groups, if an ERROR event fires within them.
+    ///
+    /// To decide whether to expand a group, all output in the group has to be held back until the span exits. Note
+    /// that this delays the output of nested spans until the outermost span exits, and only output written by
+    /// [`MakeWebConsoleWriter`] is held back.
+    ///
+    /// [`MakeWebConsoleWriter`]: crate::MakeWebConsoleWriter
+    pub fn with_expand_on_error(mut self, expand_on_error: bool) -> Self {
+        self.expand_on_error = expand_on_error;
+        self
+    }
+    /// Prefix the titles of all groups with a namespace, as `{namespace}:{span}`.
+    ///
+    /// This matches the naming of [`PerformanceEventsLayer::with_namespace`].
+    ///
+    /// [`PerformanceEventsLayer::with_namespace`]: crate::PerformanceEventsLayer::with_namespace
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+    fn buffered(&self) -> bool {
+        self.collapsed && self.expand_on_error
+    }
+}
+
+// Stored in the extensions of spans within which an ERROR event fired.
+struct ErrorSeen;
+
+struct BufferedLine {
+    log: LogDispatcher,
+    level: Level,
+    message: String,
+    options: LogOptions,
+}
+
+enum BufferedEntry {
+    Line(BufferedLine),
+    Group(BufferedGroup),
+}
+
+struct BufferedGroup {
+    title: String,
+    collapsed: bool,
+    entries: Vec<BufferedEntry>,
+}
+
+impl BufferedGroup {
+    fn flush(self) {
+        let title = JsValue::from(self.title);
+        if self.collapsed {
+            console::group_collapsed_1(&title);
+        } else {
+            console::group_1(&title);
+        }
+        for entry in self.entries {
+            match entry {
+                BufferedEntry::Line(line) => {
+                    (line.log)(line.level, &line.message, &line.options);
+                    line.options.after_logged();
+                }
+                BufferedEntry::Group(group) => group.flush(),
+            }
+        }
+        console::group_end();
+    }
+}
+
+thread_local! {
+    // The groups of the currently entered spans on this thread, held back until the outermost exits.
+    static PENDING_GROUPS: RefCell<Vec<BufferedGroup>> = RefCell::new(Vec::new());
+    // Whether the group of `open_app_group` is currently open on this thread.
+    static APP_GROUP_OPEN: Cell<bool> = Cell::new(false);
+}
+
+/// Open a top-level console group titled `name`, to nest all following console output in.
+///
+/// This is meant to be called once at startup, before any other output, e.g. via
+/// [`MakeWebConsoleWriter::with_app_group`]. The group stays open until [`close_app_group`] is called. Only one app
+/// group is opened per thread, further calls do nothing while it is open.
+///
+/// # Caveats
+///
+/// Console groups are a single stack shared by everything on the page, so the group is not limited to the output of
+/// this crate: logs of other libraries that are written while it is open are nested in it as well. Groups that are
+/// left unbalanced, e.g. by a library calling `console.groupEnd` once too often, close the app group early or keep it
+/// open around unrelated output.
+///
+/// [`MakeWebConsoleWriter::with_app_group`]: crate::MakeWebConsoleWriter::with_app_group
+pub fn open_app_group(name: &str) {
+    if !APP_GROUP_OPEN.with(|open| open.replace(true)) {
+        console::group_1(&JsValue::from(name));
+    }
+}
+
+/// Close the group opened by [`open_app_group`], if it is open on this thread.
+pub fn close_app_group() {
+    if APP_GROUP_OPEN.with(|open| open.replace(false)) {
+        console::group_end();
+    }
+}
+
+/// Whether console output is currently held back, and should be passed to [`buffer_line`].
+pub(crate) fn is_buffering() -> bool {
+    PENDING_GROUPS.with(|pending| !pending.borrow().is_empty())
+}
+
+/// Hold back a line of console output in the innermost pending group.
+pub(crate) fn buffer_line(log: LogDispatcher, level: Level, message: String, options: LogOptions) {
+    PENDING_GROUPS.with(|pending| {
+        if let Some(group) = pending.borrow_mut().last_mut() {
+            group.entries.push(BufferedEntry::Line(BufferedLine {
+                log,
+                level,
+                message,
+                options,
+            }));
+        }
+    })
+}
+
+impl<S> Layer<S> for ConsoleGroupLayer<S>
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if !self.buffered() || *event.metadata().level() != Level::ERROR {
+            return;
+        }
+        // Mark all enclosing spans, so that the path to the error is expanded
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope {
+                span.extensions_mut().replace(ErrorSeen);
+            }
+        }
+    }
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, S>) {
+        let span = ctx.span(id).expect("can't find span, this is a bug");
+        let title = match &self.namespace {
+            Some(namespace) => format!("{namespace}:{}", span.metadata().name()),
+            None => span.metadata().name().to_owned(),
+        };
+        if self.buffered() {
+            PENDING_GROUPS.with(|pending| {
+                pending.borrow_mut().push(BufferedGroup {
+                    title,
+                    collapsed: true,
+                    entries: Vec::new(),
+                })
+            });
+        } else if self.collapsed {
+            console::group_collapsed_1(&JsValue::from(title));
+        } else {
+            console::group_1(&JsValue::from(title));
+        }
+    }
+    fn on_exit(&self, id: &span::Id, ctx: Context<'_, S>) {
+        if !self.buffered() {
+            console::group_end();
+            return;
+        }
+        let span = ctx.span(id).expect("can't find span, this is a bug");
+        let error_seen = span.extensions().get::<ErrorSeen>().is_some();
+        let finished = PENDING_GROUPS.with(|pending| {
+            let mut pending = pending.borrow_mut();
+            let mut group = pending.pop()?;
+            group.collapsed = !error_seen;
+            match pending.last_mut() {
+                Some(parent) => {
+                    parent.entries.push(BufferedEntry::Group(group));
+                    None
+                }
+                None => Some(group),
+            }
+        });
+        // Flush outside of the borrow, in case logging re-enters
+        if let Some(group) = finished {
+            group.flush();
+        }
+    }
+}
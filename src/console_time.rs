@@ -0,0 +1,81 @@
+use std::marker::PhantomData;
+
+use tracing_core::{span, Subscriber};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+use web_sys::console;
+
+/// A [`Layer`] that times spans with `console.time` and `console.timeEnd`.
+///
+/// Each time a span is entered, a timer is started, which is stopped again when the span exits. The console then
+/// prints the time spent in the span, e.g. `fetch [3]: 12.3ms`. This is a console native alternative to the marks
+/// and measures of a [`PerformanceEventsLayer`], which does not require recording a profile to see the durations.
+///
+/// Timers are labelled like the entries of a [`PerformanceEventsLayer`], with the name and id of the span. If a span
+/// is entered again before it exits, the nested timer is labelled with the level of nesting, e.g. `fetch [3] (2)`,
+/// since the console only runs one timer per label.
+///
+/// [`PerformanceEventsLayer`]: crate::PerformanceEventsLayer
+pub struct ConsoleTimeLayer<S> {
+    namespace: Option<String>,
+    _inner: PhantomData<fn(S)>,
+}
+
+/// Construct a new layer timing spans in the console.
+pub fn console_time_layer<S>() -> ConsoleTimeLayer<S>
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    ConsoleTimeLayer {
+        namespace: None,
+        _inner: PhantomData,
+    }
+}
+
+impl<S> ConsoleTimeLayer<S> {
+    /// Prefix the labels of all timers with a namespace, as `{namespace}:{span} [{id}]`.
+    ///
+    /// This matches the naming of [`PerformanceEventsLayer::with_namespace`].
+    ///
+    /// [`PerformanceEventsLayer::with_namespace`]: crate::PerformanceEventsLayer::with_namespace
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+}
+
+// The labels of the running timers of a span, innermost last.
+struct RunningTimers(Vec<String>);
+
+impl<S> Layer<S> for ConsoleTimeLayer<S>
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, S>) {
+        let span = ctx.span(id).expect("can't find span, this is a bug");
+        let name = span.metadata().name();
+        let mut label = match &self.namespace {
+            Some(namespace) => format!("{namespace}:{name} [{}]", id.into_u64()),
+            None => format!("{name} [{}]", id.into_u64()),
+        };
+        let mut ext = span.extensions_mut();
+        if ext.get_mut::<RunningTimers>().is_none() {
+            ext.insert(RunningTimers(Vec::new()));
+        }
+        let RunningTimers(running) = ext.get_mut::<RunningTimers>().unwrap();
+        if !running.is_empty() {
+            label = format!("{label} ({})", running.len() + 1);
+        }
+        console::time_with_label(&label);
+        running.push(label);
+    }
+    fn on_exit(&self, id: &span::Id, ctx: Context<'_, S>) {
+        let span = ctx.span(id).expect("can't find span, this is a bug");
+        let label = span
+            .extensions_mut()
+            .get_mut::<RunningTimers>()
+            .and_then(|RunningTimers(running)| running.pop());
+        if let Some(label) = label {
+            console::time_end_with_label(&label);
+        }
+    }
+}
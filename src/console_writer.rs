@@ -1,10 +1,38 @@
-use std::io::Write;
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::HashMap,
+    io::Write,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering},
+        Arc,
+    },
+};
 
+use js_sys::{Array, Reflect};
 use tracing_core::Level;
 use tracing_subscriber::fmt::MakeWriter;
-use wasm_bindgen::JsValue;
+use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
 use web_sys::console;
 
+use crate::{
+    ansi::ansi_to_css,
+    bytes_field::{take_bytes_fields, BytesFormat},
+    compact_format::take_styled_keys,
+    console_group::{buffer_line, is_buffering, open_app_group},
+    error_alert::ErrorAlert,
+    escalation::escalated_level,
+    event_style::take_event_style,
+    gauge::take_gauges,
+    js_field::take_js_fields,
+    performance_layer::{performance_now, span_id_token},
+    quiet::{hold_line, take_held_lines, HeldLine},
+    span_depth::{
+        current_span_depth, current_span_elapsed, current_span_id, current_span_path,
+        BreadcrumbOrder,
+    },
+};
+
 /// **Discouraged** A [`MakeWriter`] emitting the written text to the [`console`].
 ///
 /// The used log method is sensitive to the level the event is emitted with.
@@ -41,7 +69,350 @@ pub struct MakeConsoleWriter;
 /// | ERROR     | console.error    |
 /// | other     | console.log      |
 pub struct MakeWebConsoleWriter {
-    use_pretty_label: bool,
+    label: PrettyLabel,
+    pretty_enabled: PrettyToggle,
+    worker_label: Option<Arc<str>>,
+    convert_ansi: bool,
+    correlation: Option<CorrelationProvider>,
+    route: Option<RouteProvider>,
+    on_error: Option<ErrorCallback>,
+    targets: Option<Vec<String>>,
+    enabled_levels: Option<LevelSet>,
+    transform: Option<MessageTransform>,
+    break_on_error: bool,
+    error_alert: ErrorAlert,
+    report_error: bool,
+    // The title of the app group, and whether it has been opened by this writer, see `with_app_group`
+    app_group: Option<(Arc<str>, Arc<AtomicBool>)>,
+    prefix_template: Option<Vec<PrefixPart>>,
+    bytes_format: BytesFormat,
+    buffer_capacity: usize,
+    whitespace: Whitespace,
+    split_lines: bool,
+    breadcrumbs: Option<(String, BreadcrumbOrder)>,
+    span_ids: bool,
+    span_elapsed: bool,
+    perf_timestamp: bool,
+    // The time of the previous message as the bits of an `f64`, NaN if there was none yet
+    relative_time: Option<Arc<AtomicU64>>,
+    // Label styles by the name of the level, in uppercase
+    label_styles: HashMap<String, String>,
+    // Custom console methods, consulted in the order they were added
+    console_methods: Vec<(MethodKey, ConsoleMethod)>,
+    level_width: Option<usize>,
+    fallback_label_style: Option<String>,
+}
+
+// How whitespace in messages is cleaned up, see `MakeWebConsoleWriter::with_collapsed_whitespace`.
+#[derive(Clone)]
+enum Whitespace {
+    Keep,
+    TrimEnd,
+    Collapse,
+    // Join the lines with a separator, see `MakeWebConsoleWriter::with_joined_lines`
+    Join(Arc<str>),
+}
+
+impl Whitespace {
+    fn apply(&self, msg: &mut Cow<'_, str>) {
+        match self {
+            Whitespace::Keep => {}
+            Whitespace::TrimEnd => match msg {
+                Cow::Borrowed(text) => *text = text.trim_end_matches('\n'),
+                Cow::Owned(text) => {
+                    let len = text.trim_end_matches('\n').len();
+                    text.truncate(len);
+                }
+            },
+            Whitespace::Collapse => {
+                let mut collapsed = String::with_capacity(msg.len());
+                for (idx, line) in msg.trim_end_matches('\n').split('\n').enumerate() {
+                    if idx > 0 {
+                        collapsed.push('\n');
+                    }
+                    let content = line.trim_start();
+                    collapsed.push_str(&line[..line.len() - content.len()]);
+                    for (idx, word) in content.split_whitespace().enumerate() {
+                        if idx > 0 {
+                            collapsed.push(' ');
+                        }
+                        collapsed.push_str(word);
+                    }
+                }
+                *msg = Cow::Owned(collapsed);
+            }
+            Whitespace::Join(separator) => {
+                let text = msg.trim_end_matches(['\r', '\n']);
+                let joined = text
+                    .split('\n')
+                    .map(|line| line.strip_suffix('\r').unwrap_or(line))
+                    .collect::<Vec<_>>()
+                    .join(separator);
+                *msg = Cow::Owned(joined);
+            }
+        }
+    }
+}
+
+// A parsed piece of the template of `MakeWebConsoleWriter::with_prefix_template`.
+enum PrefixPart {
+    Text(String),
+    Level,
+    Target,
+    File,
+    Line,
+}
+
+fn parse_prefix_template(template: &str) -> Vec<PrefixPart> {
+    let mut parts = vec![];
+    let mut text = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        text.push_str(&rest[..start]);
+        let placeholder = rest[start..]
+            .find('}')
+            .map(|end| &rest[start..start + end + 1]);
+        let part = match placeholder {
+            Some("{level}") => PrefixPart::Level,
+            Some("{target}") => PrefixPart::Target,
+            Some("{file}") => PrefixPart::File,
+            Some("{line}") => PrefixPart::Line,
+            // Not a known placeholder, keep the brace as text
+            _ => {
+                text.push('{');
+                rest = &rest[start + 1..];
+                continue;
+            }
+        };
+        if !text.is_empty() {
+            parts.push(PrefixPart::Text(std::mem::take(&mut text)));
+        }
+        parts.push(part);
+        rest = &rest[start + placeholder.map_or(0, str::len)..];
+    }
+    text.push_str(rest);
+    if !text.is_empty() {
+        parts.push(PrefixPart::Text(text));
+    }
+    parts
+}
+
+#[wasm_bindgen(inline_js = "export function debugger_break() { debugger; }")]
+extern "C" {
+    // Pauses in the debugger, if the dev-tools are open. Otherwise, this does nothing.
+    fn debugger_break();
+}
+
+#[wasm_bindgen(inline_js = r#"
+export function report_error(message) {
+    if (typeof reportError === "function") reportError(new Error(message));
+}
+"#)]
+extern "C" {
+    // Reports an `Error` with the message to the global error handlers, if `reportError` is supported.
+    fn report_error(message: &str);
+}
+
+// Looks up the correlation id of the current context, see `MakeWebConsoleWriter::with_correlation`.
+type CorrelationProvider = Arc<dyn Fn() -> Option<String> + Send + Sync>;
+// Looks up the current route of the application, see `MakeWebConsoleWriter::with_route`.
+type RouteProvider = Arc<dyn Fn() -> Option<String> + Send + Sync>;
+
+// The path of the URL of the current global scope, i.e. `location.pathname`, if there is one.
+fn location_pathname() -> Option<String> {
+    let location = Reflect::get(
+        &js_sys::global(),
+        &JsValue::from(wasm_bindgen::intern("location")),
+    )
+    .ok()
+    .filter(|location| location.is_object())?;
+    Reflect::get(&location, &JsValue::from(wasm_bindgen::intern("pathname")))
+        .ok()?
+        .as_string()
+}
+// Receives the formatted ERROR events, see `MakeWebConsoleWriter::on_error_event`.
+type ErrorCallback = Arc<dyn Fn(&str) + Send + Sync>;
+// Rewrites each message before it is logged, see `MakeWebConsoleWriter::with_message_transform`.
+type MessageTransform = Arc<dyn Fn(&str) -> Cow<'_, str> + Send + Sync>;
+
+/// A handle to switch the pretty label of a [`MakeWebConsoleWriter`] on and off at runtime.
+///
+/// Obtained from [`MakeWebConsoleWriter::pretty_toggle`]. Turning the label off makes the writer fall back to simple
+/// logging, i.e. without any `%c` styling, which is useful e.g. when an automated test harness scrapes the console
+/// text. To control it from JS, wrap it in an exported type of your own:
+///
+/// ```rust, ignore
+/// #[wasm_bindgen]
+/// pub struct ConsoleStyle(tracing_web::PrettyToggle);
+///
+/// #[wasm_bindgen]
+/// impl ConsoleStyle {
+///     pub fn set_pretty(&self, pretty: bool) {
+///         self.0.set_enabled(pretty)
+///     }
+///     pub fn is_pretty(&self) -> bool {
+///         self.0.is_enabled()
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PrettyToggle(Arc<AtomicBool>);
+
+impl PrettyToggle {
+    /// Switch the pretty label on or off.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.0.store(enabled, Ordering::Relaxed);
+    }
+    /// Check whether the pretty label is currently switched on.
+    pub fn is_enabled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// The (mutually exclusive) ways to show a pretty label in front of each message, see [`ConsoleWriterConfig`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub enum PrettyLabel {
+    /// No label is shown.
+    #[default]
+    None,
+    /// A label for the level, see [`MakeWebConsoleWriter::with_pretty_level`].
+    Level,
+    /// A label for the level, colored by span depth, see [`MakeWebConsoleWriter::with_pretty_depth`].
+    Depth,
+    /// A colored bar at the left of each line instead of a label, see [`MakeWebConsoleWriter::with_level_border`].
+    Border,
+    /// The level as written by the formatter, styled like a label, see [`MakeWebConsoleWriter::with_styled_level`].
+    Inline,
+}
+
+/// A method of the console to log with, see [`MakeWebConsoleWriter::with_console_method`].
+///
+/// The pretty label of a line follows the method, as if the line had the level usually logged with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleMethod {
+    /// `console.log`, labelled with the name of the level of the line.
+    Log,
+    /// `console.debug`, labelled as DEBUG.
+    Debug,
+    /// `console.info`, labelled as INFO.
+    Info,
+    /// `console.warn`, labelled as WARN.
+    Warn,
+    /// `console.error`, labelled as ERROR.
+    Error,
+}
+
+// What a custom console method applies to, see `MakeWebConsoleWriter::with_console_method`.
+enum MethodKey {
+    // The name of a level, in uppercase
+    Level(String),
+    // A prefix of the target
+    Target(String),
+}
+
+/// A set of levels, to enable the levels of a [`MakeWebConsoleWriter`] individually, see
+/// [`MakeWebConsoleWriter::with_enabled_levels`].
+///
+/// The set is shared between its clones, so it can be changed at runtime through a clone kept around, e.g. to switch
+/// on DEBUG and TRACE output from a settings dialog:
+///
+/// ```rust, no_run
+/// use tracing::Level;
+/// use tracing_web::{LevelSet, MakeWebConsoleWriter};
+///
+/// let levels = LevelSet::none().with(Level::WARN).with(Level::ERROR);
+/// let writer = MakeWebConsoleWriter::new().with_enabled_levels(levels.clone());
+/// // later
+/// levels.enable(Level::DEBUG);
+/// ```
+#[derive(Debug, Clone)]
+pub struct LevelSet(Arc<AtomicU8>);
+
+impl LevelSet {
+    /// A set with all levels enabled.
+    pub fn all() -> Self {
+        Self(Arc::new(AtomicU8::new(0b11111)))
+    }
+    /// A set with no levels enabled.
+    pub fn none() -> Self {
+        Self(Arc::new(AtomicU8::new(0)))
+    }
+    /// Enable `level`, and return the set.
+    pub fn with(self, level: Level) -> Self {
+        self.enable(level);
+        self
+    }
+    /// Disable `level`, and return the set.
+    pub fn without(self, level: Level) -> Self {
+        self.disable(level);
+        self
+    }
+    /// Enable `level`, for this set and all of its clones.
+    pub fn enable(&self, level: Level) {
+        self.0.fetch_or(level_bit(level), Ordering::Relaxed);
+    }
+    /// Disable `level`, for this set and all of its clones.
+    pub fn disable(&self, level: Level) {
+        self.0.fetch_and(!level_bit(level), Ordering::Relaxed);
+    }
+    /// Check whether `level` is currently enabled.
+    pub fn contains(&self, level: Level) -> bool {
+        self.0.load(Ordering::Relaxed) & level_bit(level) != 0
+    }
+}
+
+impl Default for LevelSet {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+fn level_bit(level: Level) -> u8 {
+    if level == Level::TRACE {
+        1 << 0
+    } else if level == Level::DEBUG {
+        1 << 1
+    } else if level == Level::INFO {
+        1 << 2
+    } else if level == Level::WARN {
+        1 << 3
+    } else {
+        1 << 4
+    }
+}
+
+/// All options of a [`MakeWebConsoleWriter`] that are plain data, to build it with [`MakeWebConsoleWriter::from_config`].
+///
+/// This is convenient when the options are determined programmatically. With the `serde` feature, the config can
+/// also be deserialized, e.g. from a settings object passed from JS, with the fields in camelCase.
+///
+/// ```rust, no_run
+/// use tracing_web::{ConsoleWriterConfig, MakeWebConsoleWriter, PrettyLabel};
+///
+/// let writer = MakeWebConsoleWriter::from_config(ConsoleWriterConfig {
+///     pretty_label: PrettyLabel::Level,
+///     ansi_styles: true,
+///     ..ConsoleWriterConfig::default()
+/// });
+/// ```
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, rename_all = "camelCase"))]
+pub struct ConsoleWriterConfig {
+    /// The label shown in front of each message.
+    pub pretty_label: PrettyLabel,
+    /// See [`MakeWebConsoleWriter::with_worker_label`].
+    pub worker_label: Option<String>,
+    /// See [`MakeWebConsoleWriter::with_ansi_styles`].
+    pub ansi_styles: bool,
+    /// See [`MakeWebConsoleWriter::with_targets`].
+    pub targets: Option<Vec<String>>,
+    /// See [`MakeWebConsoleWriter::with_label_style`], keyed by the name of the level.
+    pub label_styles: HashMap<String, String>,
+    /// See [`MakeWebConsoleWriter::with_fallback_label_style`].
+    pub fallback_label_style: Option<String>,
 }
 
 impl Default for MakeWebConsoleWriter {
@@ -53,8 +424,46 @@ impl Default for MakeWebConsoleWriter {
 impl MakeWebConsoleWriter {
     /// Create a default console writer, i.e. no level annotation is shown when logging a message.
     pub fn new() -> Self {
+        Self::from_config(ConsoleWriterConfig::default())
+    }
+    /// Create a console writer with the options given in `config`.
+    ///
+    /// Options that are not plain data, such as [`with_correlation`](Self::with_correlation), can be set with the
+    /// builder methods afterwards.
+    pub fn from_config(config: ConsoleWriterConfig) -> Self {
         Self {
-            use_pretty_label: false,
+            label: config.pretty_label,
+            pretty_enabled: PrettyToggle(Arc::new(AtomicBool::new(true))),
+            worker_label: config.worker_label.map(Arc::from),
+            convert_ansi: config.ansi_styles,
+            correlation: None,
+            route: None,
+            on_error: None,
+            targets: config.targets,
+            enabled_levels: None,
+            transform: None,
+            break_on_error: false,
+            error_alert: ErrorAlert::None,
+            report_error: false,
+            app_group: None,
+            prefix_template: None,
+            bytes_format: BytesFormat::default(),
+            buffer_capacity: 0,
+            whitespace: Whitespace::Keep,
+            split_lines: false,
+            breadcrumbs: None,
+            span_ids: false,
+            span_elapsed: false,
+            perf_timestamp: false,
+            relative_time: None,
+            level_width: None,
+            console_methods: vec![],
+            label_styles: config
+                .label_styles
+                .into_iter()
+                .map(|(level, style)| (level.to_ascii_uppercase(), style))
+                .collect(),
+            fallback_label_style: config.fallback_label_style,
         }
     }
     /// Enables an additional label for the log level to be shown.
@@ -63,22 +472,609 @@ impl MakeWebConsoleWriter {
     ///
     /// [`Layer::with_level(false)`]: tracing_subscriber::fmt::Layer::with_level
     pub fn with_pretty_level(mut self) -> Self {
-        self.use_pretty_label = true;
+        self.label = PrettyLabel::Level;
+        self
+    }
+    /// Enables an additional label for the log level to be shown, colored by the depth of the current span.
+    ///
+    /// Instead of the level, the color of the label then cycles through a palette as spans nest, which can be more
+    /// useful than the severity when tracing through deeply nested code. This is mutually exclusive with
+    /// [`with_pretty_level`](Self::with_pretty_level), the last option chosen wins.
+    ///
+    /// Since writers do not have access to the span context, this requires [`SpanDepthLayer`] to be installed as
+    /// well. Without it, all labels are shown with the color for depth `0`.
+    ///
+    /// [`SpanDepthLayer`]: crate::SpanDepthLayer
+    pub fn with_pretty_depth(mut self) -> Self {
+        self.label = PrettyLabel::Depth;
         self
     }
+    /// Show a thin bar at the left of each message, colored by the level, instead of a filled label.
+    ///
+    /// This is a more subdued alternative to [`with_pretty_level`](Self::with_pretty_level), similar to log viewers,
+    /// and mutually exclusive with it, the last option chosen wins. The bar uses the colors of the default labels,
+    /// styles set with [`with_label_style`](Self::with_label_style) only apply to labels. Like the labels, the bar
+    /// can be turned off at runtime with the [`pretty_toggle`](Self::pretty_toggle).
+    pub fn with_level_border(mut self) -> Self {
+        self.label = PrettyLabel::Border;
+        self
+    }
+    /// Style the level written by the formatter like the label of [`with_pretty_level`](Self::with_pretty_level).
+    ///
+    /// Instead of adding a separate label, and hiding the level of the fmt layer with [`Layer::with_level(false)`],
+    /// the level is left to the formatter, and the writer only styles it in the formatted line. The level is
+    /// recognized as the first word of the line, or the second, after a timestamp. If it is not found there, e.g.
+    /// because it is wrapped in ANSI escape codes, the line is logged without styling. Styles set with
+    /// [`with_label_style`](Self::with_label_style) apply as well. This is mutually exclusive with the other pretty
+    /// modes, the last option chosen wins.
+    ///
+    /// [`Layer::with_level(false)`]: tracing_subscriber::fmt::Layer::with_level
+    pub fn with_styled_level(mut self) -> Self {
+        self.label = PrettyLabel::Inline;
+        self
+    }
+    /// Pad the level written by the formatter to the width of `TRACE`, so that the messages line up in simple mode.
+    ///
+    /// See [`with_level_width`](Self::with_level_width) for details.
+    pub fn with_padded_level(self) -> Self {
+        self.with_level_width(Level::TRACE.as_str().len())
+    }
+    /// Pad the level written by the formatter with spaces on the right to `width` characters, e.g. `INFO ` and
+    /// `WARN `, so that the messages line up in simple mode.
+    ///
+    /// As with [`with_styled_level`](Self::with_styled_level), the level is recognized as the first word of the line,
+    /// or the second, after a timestamp, and lines where it is not found are logged as is. Pretty labels are already
+    /// of the same width, so this has no effect on lines logged with a pretty label.
+    pub fn with_level_width(mut self, width: usize) -> Self {
+        self.level_width = Some(width);
+        self
+    }
+    /// Nest all console output in a top-level group titled `name`, to set it apart from the output of the page.
+    ///
+    /// The group is opened with [`open_app_group`] when the first message is written, so that building a writer does
+    /// not touch the console. It is opened only once per writer, and can be closed again with [`close_app_group`],
+    /// e.g. to end a session. Note that the group holds all console output while it is open, also that of other
+    /// libraries, see [`open_app_group`] for caveats.
+    ///
+    /// [`open_app_group`]: crate::open_app_group
+    /// [`close_app_group`]: crate::close_app_group
+    pub fn with_app_group(mut self, name: &str) -> Self {
+        self.app_group = Some((Arc::from(name), Arc::default()));
+        self
+    }
+    /// Prefix each message with the name of the emitting (web) worker.
+    ///
+    /// This helps to tell apart the messages of multiple workers, which all end up in the same console.
+    /// With a pretty label, the name is shown as an additional label, otherwise as plain text in brackets.
+    pub fn with_worker_label(mut self, name: impl Into<String>) -> Self {
+        self.worker_label = Some(Arc::from(name.into()));
+        self
+    }
+    /// Prefix each message with the name of the emitting worker, as detected from the global scope.
+    ///
+    /// The name is read once from `self.name`, which is the name given to a worker when it was created. If it is not
+    /// available, or empty, as it usually is on the main thread, no prefix is shown.
+    pub fn with_detected_worker_label(mut self) -> Self {
+        let name = Reflect::get(
+            &js_sys::global(),
+            &JsValue::from(wasm_bindgen::intern("name")),
+        )
+        .ok()
+        .and_then(|name| name.as_string())
+        .filter(|name| !name.is_empty());
+        self.worker_label = name.map(Arc::from);
+        self
+    }
+    /// Convert the ANSI escape codes for colors and text styles into equivalent console styling.
+    ///
+    /// Browsers show ANSI escape codes, as emitted by [`Layer::with_ansi(true)`], only partially or not at all. With
+    /// this option, the styles emitted by the formatters of `tracing-subscriber`, e.g. the level colors of `Pretty`,
+    /// are shown properly instead. Only the common 8/16 color and text style codes are supported, other escape codes
+    /// are removed.
+    ///
+    /// [`Layer::with_ansi(true)`]: tracing_subscriber::fmt::Layer::with_ansi
+    pub fn with_ansi_styles(mut self) -> Self {
+        self.convert_ansi = true;
+        self
+    }
+    /// Prefix each message with a correlation id, looked up from the current context when the message is written.
+    ///
+    /// The `provider` is called once per message, e.g. to read an id propagated through a task-local in async code,
+    /// so that it does not have to be added as a field to every event. If it returns `None`, no prefix is shown.
+    /// With a pretty label, the id is shown as an additional label, otherwise as plain text in brackets.
+    pub fn with_correlation<F>(mut self, provider: F) -> Self
+    where
+        F: Fn() -> Option<String> + Send + Sync + 'static,
+    {
+        self.correlation = Some(Arc::new(provider));
+        self
+    }
+    /// Prefix each message with the current route, i.e. the path of `location.pathname`, e.g. `[/users/42]`.
+    ///
+    /// The path is read each time a message is written, so that the output of single page applications shows the
+    /// page it was logged on. If there is no location, e.g. outside of a browser, no prefix is shown. For client-side
+    /// routers that do not update the location, see [`with_route_provider`](Self::with_route_provider).
+    pub fn with_route(mut self, enabled: bool) -> Self {
+        self.route = if enabled {
+            Some(Arc::new(location_pathname))
+        } else {
+            None
+        };
+        self
+    }
+    /// Prefix each message with the current route, as returned by `provider`, e.g. `[/users/42]`.
+    ///
+    /// The `provider` is called once per message, e.g. to read the route from the state of a client-side router. If
+    /// it returns `None`, no prefix is shown.
+    pub fn with_route_provider<F>(mut self, provider: F) -> Self
+    where
+        F: Fn() -> Option<String> + Send + Sync + 'static,
+    {
+        self.route = Some(Arc::new(provider));
+        self
+    }
+    /// Additionally pass the formatted message of each ERROR event to `callback`, e.g. to forward it to telemetry.
+    ///
+    /// The callback is called with the message as written by the formatter, without the trailing newline, right
+    /// before it is logged to the console. This only applies to writers created for an event, i.e. by
+    /// [`MakeWriter::make_writer_for`], which is what the `tracing-subscriber` fmt layer uses.
+    pub fn on_error_event<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&str) + Send + Sync + 'static,
+    {
+        self.on_error = Some(Arc::new(callback));
+        self
+    }
+    /// Rewrite each message with `transform` before it is logged, e.g. to redact secrets such as tokens.
+    ///
+    /// The transform is called once per written line, with the complete formatted text, before anything else sees
+    /// it, including the callback of [`on_error_event`](Self::on_error_event). Since it runs for every line, keep it
+    /// cheap for hot logging paths, e.g. check for a pattern before allocating, and return [`Cow::Borrowed`] if
+    /// nothing needs to be changed.
+    pub fn with_message_transform<F>(mut self, transform: F) -> Self
+    where
+        F: Fn(&str) -> Cow<'_, str> + Send + Sync + 'static,
+    {
+        self.transform = Some(Arc::new(transform));
+        self
+    }
+    /// Pause in the debugger after each ERROR event is logged, with a `debugger` statement.
+    ///
+    /// This only has an effect in builds with debug assertions, and only pauses if the dev-tools are open. It helps
+    /// to catch errors in the act, with the call stack and the state of the application still available. Lines that
+    /// are not written, or held back by [`QuietLayer`], don't pause. Lines buffered by
+    /// [`ConsoleGroupLayer::with_expand_on_error`] pause once their group is written, after the span exits.
+    ///
+    /// [`QuietLayer`]: crate::QuietLayer
+    /// [`ConsoleGroupLayer::with_expand_on_error`]: crate::ConsoleGroupLayer::with_expand_on_error
+    pub fn with_break_on_error(mut self) -> Self {
+        self.break_on_error = true;
+        self
+    }
+    /// Draw attention to ERROR events with an `alert`, such as flashing the title of the document.
+    ///
+    /// This helps to notice errors during development while the console is not in view. See [`ErrorAlert`] for the
+    /// available alerts. As with [`with_break_on_error`], the alert is only triggered once the line is written to the
+    /// console.
+    ///
+    /// [`with_break_on_error`]: Self::with_break_on_error
+    pub fn with_error_alert(mut self, alert: ErrorAlert) -> Self {
+        self.error_alert = alert;
+        self
+    }
+    /// Report each ERROR event to the global error handlers with [`reportError`], like an uncaught exception.
+    ///
+    /// The formatted message is wrapped in an `Error`, which carries the stack of the logging call, and passed on to
+    /// `window.onerror`, `error` event listeners and error monitoring hooked into these. Browsers additionally show
+    /// reported errors in the console, next to the logged message. If `reportError` is not supported, nothing is
+    /// reported. Take care that error handlers do not log ERROR events themselves, as these would be reported again.
+    ///
+    /// [`reportError`]: https://developer.mozilla.org/en-US/docs/Web/API/reportError
+    pub fn with_report_error(mut self, enabled: bool) -> Self {
+        self.report_error = enabled;
+        self
+    }
+    /// Prefix each message with a template, expanded with the metadata of each event.
+    ///
+    /// The placeholders `{level}`, `{target}`, `{file}` and `{line}` are replaced with the respective metadata,
+    /// where file and line are left empty if unknown. All other text is kept as is, e.g. `"[{level}][{target}] "`.
+    /// The prefix is part of the message text, so it is also shown without a pretty label. Lines written without
+    /// metadata, i.e. by [`MakeWriter::make_writer`], are not prefixed.
+    pub fn with_prefix_template(mut self, template: &str) -> Self {
+        self.prefix_template = Some(parse_prefix_template(template));
+        self
+    }
+    /// Prefix each message with the names of the currently entered spans, e.g. `[app > page > widget]`.
+    ///
+    /// The names are joined by `separator`, in the given `order`. Unlike the fields of spans, as shown by the fmt
+    /// layer, the trail only shows where an event is emitted and is cheap to compute, which is useful for overview
+    /// logs. Messages outside of any span are not prefixed.
+    ///
+    /// Since writers do not have access to the span context, this requires [`SpanDepthLayer`] to be installed as
+    /// well. Without it, no trail is shown.
+    ///
+    /// [`SpanDepthLayer`]: crate::SpanDepthLayer
+    pub fn with_breadcrumbs(
+        mut self,
+        separator: impl Into<String>,
+        order: BreadcrumbOrder,
+    ) -> Self {
+        self.breadcrumbs = Some((separator.into(), order));
+        self
+    }
+    /// Prefix each message with the id of the innermost entered span, e.g. `[span=42]`.
+    ///
+    /// The id is formatted by [`span_id_token`](crate::span_id_token), in the same way as in the details of
+    /// [`PerformanceEventsLayer::with_span_id_token`], to find the timeline entries of the span an event was logged
+    /// in. Messages outside of any span are not prefixed.
+    ///
+    /// Since writers do not have access to the span context, this requires [`SpanDepthLayer`] to be installed as
+    /// well. Without it, no ids are shown.
+    ///
+    /// [`PerformanceEventsLayer::with_span_id_token`]: crate::PerformanceEventsLayer::with_span_id_token
+    /// [`SpanDepthLayer`]: crate::SpanDepthLayer
+    pub fn with_span_ids(mut self) -> Self {
+        self.span_ids = true;
+        self
+    }
+    /// Prefix each message with the time since the innermost entered span was first entered, e.g. `[+34ms in span]`.
+    ///
+    /// Messages outside of any span are not prefixed. Since writers do not have access to the span context, this
+    /// requires [`SpanDepthLayer`] to be installed as well, with [`SpanDepthLayer::with_elapsed_time`] to record the
+    /// time spans are entered. Without it, or if the Performance API is not available, no time is shown.
+    ///
+    /// [`SpanDepthLayer`]: crate::SpanDepthLayer
+    /// [`SpanDepthLayer::with_elapsed_time`]: crate::SpanDepthLayer::with_elapsed_time
+    pub fn with_span_elapsed(mut self) -> Self {
+        self.span_elapsed = true;
+        self
+    }
+    /// Prefix each message with the time of the event from `performance.now()`, in milliseconds, e.g. `1234.567ms`.
+    ///
+    /// The timestamps are monotonic and cheap to take, and relative to the start of the page or worker, so they line
+    /// up with the Performance timeline in the dev-tools. This is an alternative to the timers of the fmt layer, and
+    /// does not need the `time` crate. Disable the timer of the fmt layer with [`Layer::without_time`] to avoid
+    /// showing the time twice. If the Performance API is not available, no timestamp is shown.
+    ///
+    /// [`Layer::without_time`]: tracing_subscriber::fmt::Layer::without_time
+    pub fn with_perf_timestamp(mut self, enabled: bool) -> Self {
+        self.perf_timestamp = enabled;
+        self
+    }
+    /// Prefix each message with the time since the previous message, e.g. `+12ms`, to profile by eye.
+    ///
+    /// The time is taken from `performance.now()`, and the first message shows `+0ms`. All messages written by this
+    /// writer count, including messages that are suppressed, e.g. by [`with_targets`](Self::with_targets). Combined
+    /// with [`with_perf_timestamp`](Self::with_perf_timestamp), the delta is shown after the timestamp. If the
+    /// Performance API is not available, no delta is shown.
+    pub fn with_relative_time(mut self) -> Self {
+        self.relative_time = Some(Arc::new(AtomicU64::new(f64::NAN.to_bits())));
+        self
+    }
+    /// Choose the encoding of byte buffers passed with [`bytes_field`](crate::bytes_field), hex by default.
+    pub fn with_bytes_format(mut self, format: BytesFormat) -> Self {
+        self.bytes_format = format;
+        self
+    }
+    /// Reserve `capacity` bytes for the text of each message up front.
+    ///
+    /// Each message is formatted into a fresh buffer, which otherwise starts out empty and grows as needed. If most
+    /// messages are large, e.g. because they include big payloads, reserving enough space avoids reallocating the
+    /// buffer repeatedly while the message is written. This pays off when messages are written in several pieces, e.g.
+    /// by a formatter writing to the writer directly, see `tests/buffer_capacity.rs` for a comparison. The fmt layer of
+    /// `tracing-subscriber` formats each event into a buffer of its own and writes it in one piece, which grows the
+    /// buffer of the writer only once either way.
+    pub fn with_buffer_capacity(mut self, capacity: usize) -> Self {
+        self.buffer_capacity = capacity;
+        self
+    }
+    /// Remove the trailing newline written by the formatter, which shows as an empty gap after each message in some
+    /// browsers.
+    pub fn with_trimmed_newline(mut self) -> Self {
+        self.whitespace = Whitespace::TrimEnd;
+        self
+    }
+    /// Collapse runs of whitespace within each line into a single space, and remove the trailing newline.
+    ///
+    /// Line breaks and the indentation at the start of lines are kept, so that multi-line output, e.g. of the
+    /// `Pretty` formatter, is still shown as intended.
+    pub fn with_collapsed_whitespace(mut self) -> Self {
+        self.whitespace = Whitespace::Collapse;
+        self
+    }
+    /// Join the lines of multi-line messages with `separator`, e.g. `" | "`, to log each message as a single line.
+    ///
+    /// This keeps each message on one line, e.g. to search or scrape the console output. Both `\n` and `\r\n` line
+    /// breaks are replaced, and the trailing newline is removed. This is mutually exclusive with
+    /// [`with_split_lines`](Self::with_split_lines), [`with_trimmed_newline`](Self::with_trimmed_newline) and
+    /// [`with_collapsed_whitespace`](Self::with_collapsed_whitespace), the last option chosen wins.
+    pub fn with_joined_lines(mut self, separator: &str) -> Self {
+        self.whitespace = Whitespace::Join(Arc::from(separator));
+        self.split_lines = false;
+        self
+    }
+    /// Log each line of multi-line messages, e.g. of pretty-printed structs, with a separate console call.
+    ///
+    /// Some browsers collapse the line breaks in logged text, which makes multi-line messages hard to read. With this
+    /// option, the first line is logged as usual, including the pretty label and additional values, and each further
+    /// line with the console method of the same level, as plain text. A single trailing newline, as written by the
+    /// formatters, does not count as an additional line.
+    pub fn with_split_lines(mut self) -> Self {
+        if let Whitespace::Join(_) = self.whitespace {
+            self.whitespace = Whitespace::Keep;
+        }
+        self.split_lines = true;
+        self
+    }
+    /// Only write events with a target starting with one of the given prefixes, and suppress all others.
+    ///
+    /// This is a simple alternative to an [`EnvFilter`] for getting started, e.g. to only show the events of your
+    /// own crates with `with_targets(&["my_app", "my_lib"])`. Unlike a filter, the events are still recorded by all
+    /// layers, and only not written to the console.
+    ///
+    /// [`EnvFilter`]: https://docs.rs/tracing-subscriber/latest/tracing_subscriber/filter/struct.EnvFilter.html
+    pub fn with_targets(mut self, targets: &[&str]) -> Self {
+        self.targets = Some(targets.iter().map(|target| target.to_string()).collect());
+        self
+    }
+    /// Use a custom CSS style for the pretty label of the level named `level`, e.g. `"WARN"`.
+    ///
+    /// The name is matched case-insensitively against the name of the level as shown in the label. This can restyle
+    /// the built-in levels as well as levels that are not known to this crate. With
+    /// [`with_pretty_depth`](Self::with_pretty_depth), the color by depth takes precedence.
+    ///
+    /// ```rust, no_run
+    /// use tracing_web::MakeWebConsoleWriter;
+    ///
+    /// let writer = MakeWebConsoleWriter::new()
+    ///     .with_pretty_level()
+    ///     .with_label_style("warn", "color: black; padding: 0 5px; background: orange;");
+    /// ```
+    pub fn with_label_style(mut self, level: &str, style: impl Into<String>) -> Self {
+        self.label_styles
+            .insert(level.to_ascii_uppercase(), style.into());
+        self
+    }
+    /// Log the lines of the level named `level`, e.g. `"TRACE"`, with a custom console `method`.
+    ///
+    /// The name is matched case-insensitively, as in [`with_label_style`](Self::with_label_style). By default, each
+    /// level is logged with the method listed in the [table above](Self), this replaces the method of individual
+    /// levels, e.g. to log TRACE with `console.log`, so that it is not hidden with the verbose messages of the
+    /// browser. Custom methods take precedence over the escalation of [`EscalationLayer`], and are consulted in the
+    /// order they are added, together with those of [`with_target_console_method`](Self::with_target_console_method).
+    ///
+    /// [`EscalationLayer`]: crate::EscalationLayer
+    pub fn with_console_method(mut self, level: &str, method: ConsoleMethod) -> Self {
+        self.console_methods
+            .push((MethodKey::Level(level.to_ascii_uppercase()), method));
+        self
+    }
+    /// Log the lines of events with a target starting with `target` with a custom console `method`.
+    ///
+    /// For example, `with_target_console_method("my_app::audit", ConsoleMethod::Warn)` makes the events of an audit
+    /// module stand out, whatever their level. See [`with_console_method`](Self::with_console_method) for the order
+    /// in which custom methods are consulted.
+    pub fn with_target_console_method(mut self, target: &str, method: ConsoleMethod) -> Self {
+        self.console_methods
+            .push((MethodKey::Target(target.to_owned()), method));
+        self
+    }
+    /// Use a custom CSS style for the pretty label of levels without a built-in style.
+    ///
+    /// This applies to levels not known to this crate without a style from
+    /// [`with_label_style`](Self::with_label_style), and to lines written without the metadata of an event. By
+    /// default, these labels are shown in gray.
+    pub fn with_fallback_label_style(mut self, style: impl Into<String>) -> Self {
+        self.fallback_label_style = Some(style.into());
+        self
+    }
+    /// Only write events of the levels in `levels`, and suppress all others.
+    ///
+    /// Unlike a filter on the minimum level, this can e.g. enable WARN and ERROR without INFO. The set can be changed at
+    /// runtime, see [`LevelSet`]. As with [`with_targets`](Self::with_targets), the events are still recorded by
+    /// all layers, and only not written to the console.
+    pub fn with_enabled_levels(mut self, levels: LevelSet) -> Self {
+        self.enabled_levels = Some(levels);
+        self
+    }
+    /// Get a handle to switch the pretty label on and off at runtime.
+    ///
+    /// The label is switched on initially, and only shown if enabled with
+    /// [`with_pretty_level`](Self::with_pretty_level) or [`with_pretty_depth`](Self::with_pretty_depth).
+    pub fn pretty_toggle(&self) -> PrettyToggle {
+        self.pretty_enabled.clone()
+    }
+    fn target_allowed(&self, target: &str) -> bool {
+        match &self.targets {
+            Some(targets) => targets
+                .iter()
+                .any(|prefix| target.starts_with(prefix.as_str())),
+            None => true,
+        }
+    }
+    // The custom console method for the lines of an event, if any.
+    fn console_method(&self, meta: &tracing_core::Metadata<'_>) -> Option<ConsoleMethod> {
+        self.console_methods
+            .iter()
+            .find(|(key, _)| match key {
+                MethodKey::Level(level) => level == meta.level().as_str(),
+                MethodKey::Target(target) => meta.target().starts_with(target.as_str()),
+            })
+            .map(|&(_, method)| method)
+    }
+    // Open the app group before the first message, see `with_app_group`.
+    fn open_app_group_once(&self) {
+        if let Some((name, opened)) = &self.app_group {
+            if !opened.swap(true, Ordering::Relaxed) {
+                open_app_group(name);
+            }
+        }
+    }
+    fn level_enabled(&self, level: Level) -> bool {
+        self.enabled_levels
+            .as_ref()
+            .is_none_or(|levels| levels.contains(level))
+    }
+    fn expand_prefix(&self, meta: &tracing_core::Metadata<'_>) -> Vec<u8> {
+        let mut prefix = String::new();
+        let now = if self.perf_timestamp || self.relative_time.is_some() {
+            performance_now()
+        } else {
+            None
+        };
+        if let Some(now) = now.filter(|_| self.perf_timestamp) {
+            prefix.push_str(&format!("{now:.3}ms "));
+        }
+        if let (Some(previous), Some(now)) = (&self.relative_time, now) {
+            let previous = f64::from_bits(previous.swap(now.to_bits(), Ordering::Relaxed));
+            let delta = if previous.is_nan() {
+                0.0
+            } else {
+                now - previous
+            };
+            prefix.push_str(&format!("+{delta:.0}ms "));
+        }
+        if let Some(route) = self.route.as_ref().and_then(|provider| provider()) {
+            prefix.push('[');
+            prefix.push_str(&route);
+            prefix.push_str("] ");
+        }
+        for part in self.prefix_template.iter().flatten() {
+            match part {
+                PrefixPart::Text(text) => prefix.push_str(text),
+                PrefixPart::Level => prefix.push_str(meta.level().as_str()),
+                PrefixPart::Target => prefix.push_str(meta.target()),
+                PrefixPart::File => prefix.push_str(meta.file().unwrap_or_default()),
+                PrefixPart::Line => {
+                    if let Some(line) = meta.line() {
+                        prefix.push_str(&line.to_string());
+                    }
+                }
+            }
+        }
+        if let Some((separator, order)) = &self.breadcrumbs {
+            let path = current_span_path(separator, *order);
+            if !path.is_empty() {
+                prefix.push('[');
+                prefix.push_str(&path);
+                prefix.push_str("] ");
+            }
+        }
+        if let Some(id) = current_span_id().filter(|_| self.span_ids) {
+            prefix.push('[');
+            prefix.push_str(&span_id_token(&id));
+            prefix.push_str("] ");
+        }
+        if let Some(elapsed) = self.span_elapsed.then(current_span_elapsed).flatten() {
+            prefix.push_str(&format!("[+{elapsed:.0}ms in span] "));
+        }
+        prefix.into_bytes()
+    }
+    fn use_pretty_label(&self) -> bool {
+        self.label != PrettyLabel::None && self.pretty_enabled.is_enabled()
+    }
+    // The configured style of the label for `level`, if any. Without a level, the fallback style is used.
+    fn custom_label_style(&self, level: Option<Level>) -> Option<Cow<'static, str>> {
+        let style = match level {
+            Some(level) => match self.label_styles.get(level.as_str()) {
+                Some(style) => style,
+                None if !BUILTIN_LEVELS.contains(&level) => self.fallback_label_style.as_ref()?,
+                None => return None,
+            },
+            None => self.fallback_label_style.as_ref()?,
+        };
+        Some(Cow::Owned(style.clone()))
+    }
+    fn log_options(&self) -> LogOptions {
+        LogOptions {
+            worker_label: self.worker_label.clone(),
+            correlation_id: self.correlation.as_ref().and_then(|provider| provider()),
+            convert_ansi: self.convert_ansi,
+            level_width: self.level_width,
+            ..LogOptions::default()
+        }
+    }
+}
+
+// Additional information for the log dispatcher, determined when creating the writer.
+#[derive(Default)]
+pub(crate) struct LogOptions {
+    // Overrides the level specific style of the label in pretty mode
+    label_style: Option<Cow<'static, str>>,
+    // Custom style of the message in pretty mode, picked up by `EventStyleLayer`
+    message_style: Option<String>,
+    worker_label: Option<Arc<str>>,
+    correlation_id: Option<String>,
+    // Values passed along with `js_field`, logged after the message
+    js_values: Vec<(&'static str, JsValue)>,
+    // Ratios passed along with `gauge`, shown as bars after the message in pretty mode
+    gauges: Vec<(&'static str, f64)>,
+    convert_ansi: bool,
+    // The width to pad the level written by the formatter to, in simple mode
+    level_width: Option<usize>,
+    // Set for ERROR lines, see `MakeWebConsoleWriter::with_error_alert` and `with_break_on_error`
+    error_alert: ErrorAlert,
+    break_on_error: bool,
+}
+
+impl LogOptions {
+    // Draw attention to the line, once it was actually logged rather than held back or buffered.
+    pub(crate) fn after_logged(&self) {
+        self.error_alert.trigger();
+        if self.break_on_error {
+            debugger_break();
+        }
+    }
+    // The message with additional information prepended as plain text, for simple logging.
+    fn simple_message<'msg>(&self, level: Level, msg: &'msg str) -> Cow<'msg, str> {
+        let padded = self.level_width.and_then(|width| {
+            let level = level.as_str();
+            let (before, after) = split_level_token(msg, level)?;
+            Some(format!("{before}{level:<width$}{after}"))
+        });
+        let prefixes = [self.worker_label.as_deref(), self.correlation_id.as_deref()];
+        if prefixes.iter().all(Option::is_none) {
+            return padded.map_or(Cow::Borrowed(msg), Cow::Owned);
+        }
+        let mut prefixed = String::new();
+        for prefix in prefixes.into_iter().flatten() {
+            prefixed.push('[');
+            prefixed.push_str(prefix);
+            prefixed.push_str("] ");
+        }
+        prefixed.push_str(padded.as_deref().unwrap_or(msg));
+        Cow::Owned(prefixed)
+    }
+    // Whether simple logging needs more than the message as a single argument.
+    fn needs_args(&self, msg: &str) -> bool {
+        !self.js_values.is_empty() || (self.convert_ansi && msg.contains('\x1b'))
+    }
 }
 
-type LogDispatcher = fn(Level, &str);
+pub(crate) type LogDispatcher = fn(Level, &str, &LogOptions);
 
 /// Concrete [`std::io::Write`] implementation returned by [`MakeConsoleWriter`] and [`MakeWebConsoleWriter`].
 pub struct ConsoleWriter {
     buffer: Vec<u8>,
     level: Level,
     log: LogDispatcher,
+    options: LogOptions,
+    on_error: Option<ErrorCallback>,
+    transform: Option<MessageTransform>,
+    report_error: bool,
+    bytes_format: BytesFormat,
+    whitespace: Whitespace,
+    // Logs the lines after the first of multi-line messages, see `MakeWebConsoleWriter::with_split_lines`
+    continuation_log: Option<LogDispatcher>,
+    // Set for events that are not written at all, see `MakeWebConsoleWriter::with_targets`
+    suppressed: bool,
 }
 
 impl Write for ConsoleWriter {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.suppressed {
+            return Ok(buf.len());
+        }
         self.buffer.write(buf)
     }
 
@@ -90,11 +1086,113 @@ impl Write for ConsoleWriter {
 
 impl Drop for ConsoleWriter {
     fn drop(&mut self) {
+        if self.suppressed {
+            // Discard the values set aside for this event, instead of logging them with the next line
+            drop(take_js_fields());
+            drop(take_gauges());
+            drop(take_bytes_fields());
+            take_styled_keys();
+            return;
+        }
         // TODO: it's rather pointless to decoded to utf-8 here,
         //  just to re-encode as utf-16 when crossing wasm-bindgen boundaries
         // we could use TextDecoder directly to produce a
-        let message = String::from_utf8_lossy(&self.buffer);
-        (self.log)(self.level, message.as_ref())
+        let mut message = String::from_utf8_lossy(&self.buffer);
+        let bytes_fields = take_bytes_fields();
+        if !bytes_fields.is_empty() {
+            let mut with_bytes = message.trim_end_matches('\n').to_owned();
+            for (name, bytes) in bytes_fields {
+                with_bytes.push(' ');
+                with_bytes.push_str(name);
+                with_bytes.push('=');
+                with_bytes.push_str(&self.bytes_format.encode(&bytes));
+            }
+            with_bytes.push('\n');
+            message = Cow::Owned(with_bytes);
+        }
+        if let Some(transform) = &self.transform {
+            if let Cow::Owned(transformed) = transform(&message) {
+                message = Cow::Owned(transformed);
+            }
+        }
+        self.whitespace.apply(&mut message);
+        if take_styled_keys() {
+            // The keys highlighted by `CompactConsoleFormat` are styled even without `with_ansi_styles`
+            self.options.convert_ansi = true;
+        }
+        if !self.options.convert_ansi && !ANSI_CHECKED.swap(true, Ordering::Relaxed) {
+            warn_if_ansi(&message);
+        }
+        if let Some(on_error) = &self.on_error {
+            on_error(message.trim_end_matches('\n'));
+        }
+        if self.report_error {
+            report_error(message.trim_end_matches('\n'));
+        }
+        self.options.js_values = take_js_fields();
+        self.options.gauges = take_gauges();
+        let mut message = message.into_owned();
+        let mut continuation = vec![];
+        if let Some(log) = self.continuation_log {
+            // A single trailing newline does not make a message multi-line
+            let text = message.strip_suffix('\n').unwrap_or(&message);
+            if let Some(end) = text.find('\n') {
+                continuation = text[end + 1..]
+                    .split('\n')
+                    .map(|line| HeldLine {
+                        log,
+                        level: self.level,
+                        message: line.to_owned(),
+                        options: LogOptions {
+                            convert_ansi: self.options.convert_ansi,
+                            ..LogOptions::default()
+                        },
+                    })
+                    .collect();
+                message.truncate(end);
+            }
+        }
+        let line = HeldLine {
+            log: self.log,
+            level: self.level,
+            message,
+            options: std::mem::take(&mut self.options),
+        };
+        for (idx, line) in std::iter::once(line).chain(continuation).enumerate() {
+            let line = match hold_line(line) {
+                Some(line) => line,
+                None => continue,
+            };
+            if idx == 0 && line.level == Level::ERROR {
+                // Show the lines held back by `QuietLayer` as context of the error
+                for held in take_held_lines() {
+                    emit_line(held);
+                }
+            }
+            emit_line(line);
+        }
+    }
+}
+
+// Whether the first line written has been checked for ANSI escape codes.
+static ANSI_CHECKED: AtomicBool = AtomicBool::new(false);
+
+// Leaving ANSI output of the fmt layer switched on is a common mistake, which garbles the console output.
+fn warn_if_ansi(message: &str) {
+    if message.contains("\x1b[") {
+        console::warn_1(&JsValue::from(
+            "tracing-web: the console output contains ANSI escape codes. Use `.with_ansi(false)` on the fmt layer, \
+             or show them as styles with `MakeWebConsoleWriter::with_ansi_styles()`.",
+        ));
+    }
+}
+
+fn emit_line(line: HeldLine) {
+    if is_buffering() {
+        buffer_line(line.log, line.level, line.message, line.options);
+    } else {
+        (line.log)(line.level, &line.message, &line.options);
+        line.options.after_logged();
     }
 }
 
@@ -104,61 +1202,309 @@ impl Drop for ConsoleWriter {
 // fn pointers for the applicable dispatcher.
 
 trait LogImpl {
-    fn log_simple(level: Level, msg: &str);
-    fn log_pretty(level: Level, msg: &str);
+    fn log_simple(level: Level, msg: &str, options: &LogOptions);
+    fn log_pretty(level: Level, msg: &str, options: &LogOptions);
+    fn log_border(level: Level, msg: &str, options: &LogOptions);
+    fn log_inline(level: Level, msg: &str, options: &LogOptions);
 }
 
 const MESSAGE_STYLE: &str = "background: inherit; color: inherit;";
+const WORKER_LABEL_STYLE: &str = "color: white; padding: 0 5px; background: #555753;";
+const GAUGE_STYLE: &str = "background: #4E9A06;";
+// The width of the bar of a full gauge, in pixels
+const GAUGE_WIDTH: f64 = 100.0;
+const CORRELATION_LABEL_STYLE: &str = "color: #2E3436; padding: 0 5px; background: #D3D7CF;";
+// Note: `text-transform` might not have perfect browser support, but is available in at least Firefox and Chrome at the time of writing
+const FALLBACK_LABEL_STYLE: &str =
+    "color: white; font-weight: bold; padding: 0 5px; background: #424242; text-transform: uppercase;";
+const FALLBACK_BORDER_COLOR: &str = "#424242";
+
+// Split a formatted line around the level written by the formatter, which is the first word, or the second after a
+// timestamp. Returns the text in front of the level and the text after it.
+fn split_level_token<'msg>(msg: &'msg str, level: &str) -> Option<(&'msg str, &'msg str)> {
+    let mut offset = 0;
+    for _ in 0..2 {
+        let rest = &msg[offset..];
+        let start = offset + (rest.len() - rest.trim_start().len());
+        let end = msg[start..]
+            .find(char::is_whitespace)
+            .map_or(msg.len(), |len| start + len);
+        if start < end && &msg[start..end] == level {
+            return Some((&msg[..start], &msg[end..]));
+        }
+        offset = end;
+    }
+    None
+}
+
+// The arguments of a console call showing a number of styled labels in front of the message, and additional values
+// after it, assembled from left to right. The texts are passed as separate arguments, so they can not be
+// misinterpreted as format specifiers.
+struct ConsoleArgs {
+    fmt: String,
+    args: Vec<JsValue>,
+    // Whether additional styling, such as gauges, can be used
+    pretty: bool,
+}
+
+impl ConsoleArgs {
+    fn new() -> Self {
+        Self {
+            fmt: String::new(),
+            args: vec![],
+            pretty: false,
+        }
+    }
+    fn pretty(options: &LogOptions) -> Self {
+        Self::new().with_prefix_labels(options)
+    }
+    // Enable pretty styling, and add the labels of the worker and correlation id, if any.
+    fn with_prefix_labels(mut self, options: &LogOptions) -> Self {
+        self.pretty = true;
+        if let Some(worker) = &options.worker_label {
+            self.push_label(worker, WORKER_LABEL_STYLE);
+        }
+        if let Some(id) = &options.correlation_id {
+            self.push_label(id, CORRELATION_LABEL_STYLE);
+        }
+        self
+    }
+    // A bar in `color` at the start of the line, followed by some space, see `MakeWebConsoleWriter::with_level_border`.
+    fn push_border(&mut self, color: &str) {
+        self.fmt.push_str("%c%c");
+        self.args.push(JsValue::from(format!(
+            "border-left: 4px solid {color}; padding-left: 6px;"
+        )));
+        self.args
+            .push(JsValue::from(wasm_bindgen::intern(MESSAGE_STYLE)));
+    }
+    // The part of the line up to the level in `style`, see `MakeWebConsoleWriter::with_styled_level`. Returns the rest
+    // of the line, to be passed to `finish`.
+    fn push_inline_level<'msg>(&mut self, msg: &'msg str, level: &str, style: &str) -> &'msg str {
+        match split_level_token(msg, level) {
+            Some((before, after)) => {
+                self.fmt.push_str("%s%c%s%c");
+                self.args.push(JsValue::from(before));
+                self.args.push(JsValue::from(wasm_bindgen::intern(style)));
+                self.args.push(JsValue::from(level));
+                self.args
+                    .push(JsValue::from(wasm_bindgen::intern(MESSAGE_STYLE)));
+                after
+            }
+            None => msg,
+        }
+    }
+    fn push_label(&mut self, text: &str, style: &str) {
+        self.fmt.push_str("%c%s%c ");
+        self.args.push(JsValue::from(wasm_bindgen::intern(style)));
+        self.args.push(JsValue::from(text));
+        self.args
+            .push(JsValue::from(wasm_bindgen::intern(MESSAGE_STYLE)));
+    }
+    fn finish(mut self, msg: &str, options: &LogOptions) -> Array {
+        if options.convert_ansi && msg.contains('\x1b') {
+            for (text, style) in ansi_to_css(msg) {
+                self.fmt.push_str("%c%s");
+                self.args.push(JsValue::from(style));
+                self.args.push(JsValue::from(text));
+            }
+            self.fmt.push_str("%c");
+            self.args
+                .push(JsValue::from(wasm_bindgen::intern(MESSAGE_STYLE)));
+        } else if let Some(style) = options.message_style.as_deref().filter(|_| self.pretty) {
+            self.fmt.push_str("%c%s%c");
+            self.args.push(JsValue::from(style));
+            self.args.push(JsValue::from(msg));
+            self.args
+                .push(JsValue::from(wasm_bindgen::intern(MESSAGE_STYLE)));
+        } else {
+            self.fmt.push_str("%s");
+            self.args.push(JsValue::from(msg));
+        }
+        for (name, value) in &options.js_values {
+            self.fmt.push_str(" %s=%o");
+            self.args.push(JsValue::from(*name));
+            self.args.push(value.clone());
+        }
+        if self.pretty {
+            for (name, ratio) in &options.gauges {
+                let ratio = ratio.clamp(0.0, 1.0);
+                self.fmt.push_str(" %s %c %c%s");
+                self.args.push(JsValue::from(*name));
+                self.args.push(JsValue::from(format!(
+                    "{GAUGE_STYLE} padding-right: {}px;",
+                    (ratio * GAUGE_WIDTH).round()
+                )));
+                self.args
+                    .push(JsValue::from(wasm_bindgen::intern(MESSAGE_STYLE)));
+                self.args
+                    .push(JsValue::from(format!(" {:.0}%", ratio * 100.0)));
+            }
+        }
+        std::iter::once(JsValue::from(self.fmt))
+            .chain(self.args)
+            .collect()
+    }
+}
+
+thread_local! {
+    // Whether the console methods looked up so far are available, see `has_console_method`.
+    static CONSOLE_METHODS: RefCell<Vec<(&'static str, bool)>> = RefCell::new(Vec::new());
+}
+
+// Check, once per method, whether `console[method]` is a function. Some bundlers strip e.g. `console.debug` from
+// production builds, and calling a missing method would throw.
+fn has_console_method(method: &'static str) -> bool {
+    CONSOLE_METHODS.with(|methods| {
+        if let Some(&(_, available)) = methods.borrow().iter().find(|(name, _)| *name == method) {
+            return available;
+        }
+        let available = Reflect::get(
+            &js_sys::global(),
+            &JsValue::from(wasm_bindgen::intern("console")),
+        )
+        .and_then(|console| Reflect::get(&console, &JsValue::from(wasm_bindgen::intern(method))))
+        .is_ok_and(|method| method.is_function());
+        methods.borrow_mut().push((method, available));
+        available
+    })
+}
+
 macro_rules! make_log_impl {
     ($T:ident {
+        method: $m:expr,
         simple: $s:expr,
         pretty: {
-            log: $p:expr, fmt: $f:expr, label_style: $l:expr $(,)?
+            log: $p:expr, label: $f:expr, label_style: $l:expr, border_color: $b:expr $(,)?
         } $(,)?
     }) => {
         struct $T;
         impl LogImpl for $T {
             #[inline(always)]
-            fn log_simple(_level: Level, msg: &str) {
-                $s(&JsValue::from(msg));
+            fn log_simple(level: Level, msg: &str, options: &LogOptions) {
+                let msg = options.simple_message(level, msg);
+                if !options.needs_args(&msg) {
+                    let msg = JsValue::from(msg.as_ref());
+                    if has_console_method($m) {
+                        $s(&msg);
+                    } else if has_console_method("log") {
+                        console::log_1(&msg);
+                    }
+                } else {
+                    let args = ConsoleArgs::new().finish(&msg, options);
+                    if has_console_method($m) {
+                        $p(&args);
+                    } else if has_console_method("log") {
+                        console::log(&args);
+                    }
+                }
+            }
+            #[inline(always)]
+            fn log_pretty(_level: Level, msg: &str, options: &LogOptions) {
+                let mut args = ConsoleArgs::pretty(options);
+                args.push_label(
+                    wasm_bindgen::intern($f),
+                    options.label_style.as_deref().unwrap_or($l),
+                );
+                let args = args.finish(msg, options);
+                if has_console_method($m) {
+                    $p(&args);
+                } else if has_console_method("log") {
+                    console::log(&args);
+                }
             }
             #[inline(always)]
-            fn log_pretty(_level: Level, msg: &str) {
-                let fmt = JsValue::from(wasm_bindgen::intern($f));
-                let label_style = JsValue::from(wasm_bindgen::intern($l));
-                let msg_style = JsValue::from(wasm_bindgen::intern(MESSAGE_STYLE));
-                $p(&fmt, &label_style, &msg_style, &JsValue::from(msg));
+            fn log_border(_level: Level, msg: &str, options: &LogOptions) {
+                let mut args = ConsoleArgs::new();
+                args.push_border($b);
+                let args = args.with_prefix_labels(options);
+                let args = args.finish(msg, options);
+                if has_console_method($m) {
+                    $p(&args);
+                } else if has_console_method("log") {
+                    console::log(&args);
+                }
+            }
+            #[inline(always)]
+            fn log_inline(_level: Level, msg: &str, options: &LogOptions) {
+                let mut args = ConsoleArgs::pretty(options);
+                let rest = args.push_inline_level(
+                    msg,
+                    wasm_bindgen::intern($f.trim_start()),
+                    options.label_style.as_deref().unwrap_or($l),
+                );
+                let args = args.finish(rest, options);
+                if has_console_method($m) {
+                    $p(&args);
+                } else if has_console_method("log") {
+                    console::log(&args);
+                }
             }
         }
     };
 }
 
 // Even though console.trace exists and generates stack traces, it logs with level: info, so leads to verbose logs, so log with debug
-make_log_impl!(LogLevelTrace { simple: console::debug_1, pretty: { log: console::debug_4, fmt: "%cTRACE%c %s", label_style: "color: white; font-weight: bold; padding: 0 5px; background: #75507B;" } });
-make_log_impl!(LogLevelDebug { simple: console::debug_1, pretty: { log: console::debug_4, fmt: "%cDEBUG%c %s", label_style: "color: white; font-weight: bold; padding: 0 5px; background: #3465A4;" } });
-make_log_impl!(LogLevelInfo  { simple: console::info_1,  pretty: { log: console::info_4,  fmt: "%c INFO%c %s", label_style: "color: white; font-weight: bold; padding: 0 5px; background: #4E9A06;" } });
-make_log_impl!(LogLevelWarn  { simple: console::warn_1,  pretty: { log: console::warn_4,  fmt: "%c WARN%c %s", label_style: "color: white; font-weight: bold; padding: 0 5px; background: #C4A000;" } });
-make_log_impl!(LogLevelError { simple: console::error_1, pretty: { log: console::error_4, fmt: "%cERROR%c %s", label_style: "color: white; font-weight: bold; padding: 0 5px; background: #CC0000;" } });
+make_log_impl!(LogLevelTrace { method: "debug", simple: console::debug_1, pretty: { log: console::debug, label: "TRACE", label_style: "color: white; font-weight: bold; padding: 0 5px; background: #75507B;", border_color: "#75507B" } });
+make_log_impl!(LogLevelDebug { method: "debug", simple: console::debug_1, pretty: { log: console::debug, label: "DEBUG", label_style: "color: white; font-weight: bold; padding: 0 5px; background: #3465A4;", border_color: "#3465A4" } });
+make_log_impl!(LogLevelInfo  { method: "info",  simple: console::info_1,  pretty: { log: console::info,  label: " INFO", label_style: "color: white; font-weight: bold; padding: 0 5px; background: #4E9A06;", border_color: "#4E9A06" } });
+make_log_impl!(LogLevelWarn  { method: "warn",  simple: console::warn_1,  pretty: { log: console::warn,  label: " WARN", label_style: "color: white; font-weight: bold; padding: 0 5px; background: #C4A000;", border_color: "#C4A000" } });
+make_log_impl!(LogLevelError { method: "error", simple: console::error_1, pretty: { log: console::error, label: "ERROR", label_style: "color: white; font-weight: bold; padding: 0 5px; background: #CC0000;", border_color: "#CC0000" } });
 
 // This impl serves as a fallback for potential additions to tracing's levels that I can't forsee. It should not be reachable in code as of the time of writing, but might be in future additions to tracing.
 struct LogLevelFallback;
 impl LogImpl for LogLevelFallback {
     #[inline(always)]
-    fn log_simple(_level: Level, msg: &str) {
-        console::log_1(&JsValue::from(msg))
+    fn log_simple(level: Level, msg: &str, options: &LogOptions) {
+        if !has_console_method("log") {
+            return;
+        }
+        let msg = options.simple_message(level, msg);
+        if !options.needs_args(&msg) {
+            console::log_1(&JsValue::from(msg.as_ref()))
+        } else {
+            console::log(&ConsoleArgs::new().finish(&msg, options))
+        }
     }
 
     #[inline(always)]
-    fn log_pretty(level: Level, msg: &str) {
-        let fmt = JsValue::from(wasm_bindgen::intern("%c%s%c %s"));
-        let label_level = JsValue::from(format!("{}", level));
-        // Note: `text-transform` might not have perfect browser support, but is available in at least Firefox and Chrome at the time of writing
-        let label_style = JsValue::from(wasm_bindgen::intern(
-            "color: white; font-weight: bold; padding: 0 5px; background: #424242; text-transform: uppercase;",
-        ));
-        let msg_style = JsValue::from(wasm_bindgen::intern(MESSAGE_STYLE));
-        let msg = JsValue::from(msg);
-        console::log_5(&fmt, &label_style, &label_level, &msg_style, &msg)
+    fn log_pretty(level: Level, msg: &str, options: &LogOptions) {
+        if !has_console_method("log") {
+            return;
+        }
+        let mut args = ConsoleArgs::pretty(options);
+        let label_level = format!("{}", level);
+        let label_style = options
+            .label_style
+            .as_deref()
+            .unwrap_or(FALLBACK_LABEL_STYLE);
+        args.push_label(&label_level, label_style);
+        console::log(&args.finish(msg, options))
+    }
+
+    #[inline(always)]
+    fn log_border(_level: Level, msg: &str, options: &LogOptions) {
+        if !has_console_method("log") {
+            return;
+        }
+        let mut args = ConsoleArgs::new();
+        args.push_border(FALLBACK_BORDER_COLOR);
+        let args = args.with_prefix_labels(options);
+        console::log(&args.finish(msg, options))
+    }
+
+    #[inline(always)]
+    fn log_inline(level: Level, msg: &str, options: &LogOptions) {
+        if !has_console_method("log") {
+            return;
+        }
+        let mut args = ConsoleArgs::pretty(options);
+        let label_style = options
+            .label_style
+            .as_deref()
+            .unwrap_or(FALLBACK_LABEL_STYLE);
+        let rest = args.push_inline_level(msg, level.as_str(), label_style);
+        console::log(&args.finish(rest, options))
     }
 }
 
@@ -182,8 +1528,55 @@ impl LogImplStyle for PrettyStyle {
         L::log_pretty
     }
 }
+struct BorderStyle;
+impl LogImplStyle for BorderStyle {
+    #[inline(always)]
+    fn get_dispatch<L: LogImpl>(&self) -> LogDispatcher {
+        L::log_border
+    }
+}
+struct InlineStyle;
+impl LogImplStyle for InlineStyle {
+    #[inline(always)]
+    fn get_dispatch<L: LogImpl>(&self) -> LogDispatcher {
+        L::log_inline
+    }
+}
+
+// The levels with a built-in label style, all others are logged by `LogLevelFallback`.
+const BUILTIN_LEVELS: [Level; 5] = [
+    Level::TRACE,
+    Level::DEBUG,
+    Level::INFO,
+    Level::WARN,
+    Level::ERROR,
+];
 
-fn select_dispatcher(style: impl LogImplStyle, level: Level) -> LogDispatcher {
+// Label styles used when coloring by span depth, cycled through as spans nest deeper.
+const DEPTH_LABEL_STYLES: [&str; 6] = [
+    "color: white; font-weight: bold; padding: 0 5px; background: #555753;",
+    "color: white; font-weight: bold; padding: 0 5px; background: #3465A4;",
+    "color: white; font-weight: bold; padding: 0 5px; background: #4E9A06;",
+    "color: white; font-weight: bold; padding: 0 5px; background: #75507B;",
+    "color: white; font-weight: bold; padding: 0 5px; background: #06989A;",
+    "color: white; font-weight: bold; padding: 0 5px; background: #C4A000;",
+];
+
+// Select the dispatcher for `level`, or for a custom console `method` if there is one.
+fn select_dispatcher(
+    style: impl LogImplStyle,
+    level: Level,
+    method: Option<ConsoleMethod>,
+) -> LogDispatcher {
+    if let Some(method) = method {
+        return match method {
+            ConsoleMethod::Log => style.get_dispatch::<LogLevelFallback>(),
+            ConsoleMethod::Debug => style.get_dispatch::<LogLevelDebug>(),
+            ConsoleMethod::Info => style.get_dispatch::<LogLevelInfo>(),
+            ConsoleMethod::Warn => style.get_dispatch::<LogLevelWarn>(),
+            ConsoleMethod::Error => style.get_dispatch::<LogLevelError>(),
+        };
+    }
     if level == Level::TRACE {
         style.get_dispatch::<LogLevelTrace>()
     } else if level == Level::DEBUG {
@@ -199,12 +1592,25 @@ fn select_dispatcher(style: impl LogImplStyle, level: Level) -> LogDispatcher {
     }
 }
 
+/// Log a line with the console method for `level`, for output that is not written by a [`ConsoleWriter`].
+pub(crate) fn log_line(level: Level, message: String, pretty: bool) {
+    let log = if pretty {
+        select_dispatcher(PrettyStyle, level, None)
+    } else {
+        select_dispatcher(SimpleStyle, level, None)
+    };
+    emit_line(HeldLine {
+        log,
+        level,
+        message,
+        options: LogOptions::default(),
+    });
+}
+
 impl MakeConsoleWriter {
     // "upgrade" to a MakeWebConsoleWriter, mainly to unify code paths.
     fn upgrade(&self) -> MakeWebConsoleWriter {
-        MakeWebConsoleWriter {
-            use_pretty_label: false,
-        }
+        MakeWebConsoleWriter::new()
     }
 }
 impl<'a> MakeWriter<'a> for MakeConsoleWriter {
@@ -223,28 +1629,74 @@ impl<'a> MakeWriter<'a> for MakeWebConsoleWriter {
     type Writer = ConsoleWriter;
 
     fn make_writer(&'a self) -> Self::Writer {
+        self.open_app_group_once();
         ConsoleWriter {
-            buffer: vec![],
+            buffer: Vec::with_capacity(self.buffer_capacity),
             level: Level::TRACE, // if no level is known, assume the most detailed
-            log: if self.use_pretty_label {
-                PrettyStyle.get_dispatch::<LogLevelFallback>()
-            } else {
-                SimpleStyle.get_dispatch::<LogLevelFallback>()
+            log: match (self.use_pretty_label(), self.label) {
+                (false, _) => SimpleStyle.get_dispatch::<LogLevelFallback>(),
+                (true, PrettyLabel::Border) => BorderStyle.get_dispatch::<LogLevelFallback>(),
+                (true, PrettyLabel::Inline) => InlineStyle.get_dispatch::<LogLevelFallback>(),
+                (true, _) => PrettyStyle.get_dispatch::<LogLevelFallback>(),
             },
+            options: LogOptions {
+                label_style: self.custom_label_style(None),
+                ..self.log_options()
+            },
+            on_error: None,
+            transform: self.transform.clone(),
+            report_error: false,
+            bytes_format: self.bytes_format,
+            whitespace: self.whitespace.clone(),
+            continuation_log: self
+                .split_lines
+                .then(|| SimpleStyle.get_dispatch::<LogLevelFallback>()),
+            suppressed: false,
         }
     }
 
     fn make_writer_for(&'a self, meta: &tracing_core::Metadata<'_>) -> Self::Writer {
+        self.open_app_group_once();
         let level = *meta.level();
-        let log_fn = if self.use_pretty_label {
-            select_dispatcher(PrettyStyle, level)
-        } else {
-            select_dispatcher(SimpleStyle, level)
+        // Only the console method is escalated, the level of the line is kept for everything else
+        let dispatch_level = escalated_level(level);
+        let method = self.console_method(meta);
+        let log_fn = match (self.use_pretty_label(), self.label) {
+            (false, _) => select_dispatcher(SimpleStyle, dispatch_level, method),
+            (true, PrettyLabel::Border) => select_dispatcher(BorderStyle, dispatch_level, method),
+            (true, PrettyLabel::Inline) => select_dispatcher(InlineStyle, dispatch_level, method),
+            (true, _) => select_dispatcher(PrettyStyle, dispatch_level, method),
         };
+        let mut options = self.log_options();
+        options.message_style = take_event_style();
+        if level == Level::ERROR {
+            options.error_alert = self.error_alert;
+            options.break_on_error = cfg!(debug_assertions) && self.break_on_error;
+        }
+        if self.label == PrettyLabel::Depth {
+            let depth = current_span_depth();
+            options.label_style = Some(Cow::Borrowed(
+                DEPTH_LABEL_STYLES[depth % DEPTH_LABEL_STYLES.len()],
+            ));
+        } else {
+            options.label_style = self.custom_label_style(Some(level));
+        }
+        let mut buffer = self.expand_prefix(meta);
+        buffer.reserve(self.buffer_capacity);
         ConsoleWriter {
-            buffer: vec![],
+            buffer,
             level,
             log: log_fn,
+            options,
+            on_error: self.on_error.clone().filter(|_| level == Level::ERROR),
+            transform: self.transform.clone(),
+            report_error: self.report_error && level == Level::ERROR,
+            bytes_format: self.bytes_format,
+            whitespace: self.whitespace.clone(),
+            continuation_log: self
+                .split_lines
+                .then(|| select_dispatcher(SimpleStyle, dispatch_level, method)),
+            suppressed: !self.target_allowed(meta.target()) || !self.level_enabled(level),
         }
     }
 }
@@ -0,0 +1,209 @@
+use std::fmt::{self, Write};
+
+use tracing_core::field::{Field, Visit};
+use tracing_subscriber::{
+    field::RecordFields,
+    fmt::{format::Writer, FormatFields},
+};
+
+/// A [`FormatFields`] that writes numeric fields holding a duration with the most appropriate unit.
+///
+/// Fields are recognized as durations by the suffix of their name, by default `_ns`, `_us`, `_ms` and `_s`. Their
+/// value is scaled to ns, µs, ms or s, whichever is the largest unit the value is at least `1` in, so that e.g.
+/// `elapsed_s = 0.0034` is written as `elapsed_s=3.4ms`. All other fields are written as `name=value`, with the
+/// message, if any, in front.
+///
+/// ```rust, no_run
+/// use tracing_web::DurationFields;
+/// # use tracing_subscriber::Registry;
+///
+/// let fmt_layer = tracing_subscriber::fmt::layer::<Registry>().fmt_fields(DurationFields::new());
+/// ```
+#[derive(Debug, Clone)]
+pub struct DurationFields {
+    // The recognized suffixes, with the length of their unit in seconds
+    suffixes: Vec<(&'static str, f64)>,
+    pretty_durations: bool,
+}
+
+impl Default for DurationFields {
+    fn default() -> Self {
+        Self {
+            suffixes: vec![("_ns", 1e-9), ("_us", 1e-6), ("_ms", 1e-3), ("_s", 1.0)],
+            pretty_durations: false,
+        }
+    }
+}
+
+impl DurationFields {
+    /// Create a new field formatter recognizing the default suffixes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Replace the recognized suffixes, each with the length of its unit in seconds.
+    ///
+    /// For example, `&[("_millis", 1e-3), ("_secs", 1.0)]` recognizes `load_millis` and `idle_secs`.
+    pub fn with_suffixes(mut self, suffixes: &[(&'static str, f64)]) -> Self {
+        self.suffixes = suffixes.to_vec();
+        self
+    }
+    /// Also shorten values recorded with the `Debug` output of a [`Duration`](std::time::Duration), regardless of
+    /// the name of the field.
+    ///
+    /// `tracing::info!(elapsed = ?duration)` records e.g. `1.234567891s`, which is then written as `1.235s`, in the
+    /// same way as fields with a duration suffix. Values are recognized by their format alone, a number followed by
+    /// `s`, `ms`, `µs` or `ns`, so this is opt-in, in case other `Debug` output happens to look the same.
+    pub fn with_pretty_durations(mut self) -> Self {
+        self.pretty_durations = true;
+        self
+    }
+    fn unit_of(&self, field: &Field) -> Option<f64> {
+        self.suffixes
+            .iter()
+            .find(|(suffix, _)| field.name().ends_with(suffix))
+            .map(|&(_, unit)| unit)
+    }
+}
+
+// Write a duration in seconds with the largest unit it is at least 1 in, with up to 3 decimals.
+fn write_duration(out: &mut String, seconds: f64) {
+    const UNITS: [(&str, f64); 4] = [("s", 1.0), ("ms", 1e-3), ("µs", 1e-6), ("ns", 1e-9)];
+    let (name, unit) = UNITS
+        .iter()
+        .find(|&&(_, unit)| seconds.abs() >= unit)
+        .unwrap_or(&UNITS[UNITS.len() - 1]);
+    let value = format!("{:.3}", seconds / unit);
+    let value = value.trim_end_matches('0').trim_end_matches('.');
+    let _ = write!(out, "{value}{name}");
+}
+
+// Parse the `Debug` output of a `Duration`, such as `1.5s` or `340ms`, into seconds.
+fn parse_debug_duration(text: &str) -> Option<f64> {
+    const UNITS: [(&str, f64); 4] = [("ns", 1e-9), ("µs", 1e-6), ("ms", 1e-3), ("s", 1.0)];
+    let (number, unit) = UNITS
+        .iter()
+        .find_map(|&(name, unit)| Some((text.strip_suffix(name)?, unit)))?;
+    if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+    number.parse::<f64>().ok().map(|value| value * unit)
+}
+
+struct DurationVisitor<'a> {
+    format: &'a DurationFields,
+    message: Option<String>,
+    fields: String,
+}
+
+impl DurationVisitor<'_> {
+    fn write_name(&mut self, field: &Field) {
+        if !self.fields.is_empty() {
+            self.fields.push(' ');
+        }
+        self.fields.push_str(field.name());
+        self.fields.push('=');
+    }
+    fn record_number(&mut self, field: &Field, value: f64, fallback: &dyn fmt::Debug) {
+        self.write_name(field);
+        match self.format.unit_of(field) {
+            Some(unit) if value.is_finite() => write_duration(&mut self.fields, value * unit),
+            _ => {
+                let _ = write!(self.fields, "{:?}", fallback);
+            }
+        }
+    }
+}
+
+impl Visit for DurationVisitor<'_> {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.record_number(field, value, &value);
+    }
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.record_number(field, value as f64, &value);
+    }
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.record_number(field, value as f64, &value);
+    }
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = Some(format!("{:?}", value));
+        } else {
+            self.write_name(field);
+            let text = format!("{:?}", value);
+            match parse_debug_duration(&text).filter(|_| self.format.pretty_durations) {
+                Some(seconds) => write_duration(&mut self.fields, seconds),
+                None => self.fields.push_str(&text),
+            }
+        }
+    }
+}
+
+impl<'writer> FormatFields<'writer> for DurationFields {
+    fn format_fields<R: RecordFields>(
+        &self,
+        mut writer: Writer<'writer>,
+        fields: R,
+    ) -> fmt::Result {
+        let mut visitor = DurationVisitor {
+            format: self,
+            message: None,
+            fields: String::new(),
+        };
+        fields.record(&mut visitor);
+
+        match (&visitor.message, visitor.fields.is_empty()) {
+            (Some(message), true) => writer.write_str(message),
+            (Some(message), false) => write!(writer, "{message} {}", visitor.fields),
+            (None, _) => writer.write_str(&visitor.fields),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_debug_duration, write_duration};
+
+    fn written(seconds: f64) -> String {
+        let mut out = String::new();
+        write_duration(&mut out, seconds);
+        out
+    }
+
+    fn assert_parsed(text: &str, seconds: f64) {
+        let parsed = parse_debug_duration(text).unwrap();
+        assert!(
+            (parsed - seconds).abs() < seconds * 1e-9,
+            "{text} parsed as {parsed}"
+        );
+    }
+
+    #[test]
+    fn write_picks_largest_unit() {
+        assert_eq!(written(1.234567891), "1.235s");
+        assert_eq!(written(60.0), "60s");
+        assert_eq!(written(0.34), "340ms");
+        assert_eq!(written(0.0034), "3.4ms");
+        assert_eq!(written(12e-6), "12µs");
+        assert_eq!(written(5e-9), "5ns");
+        assert_eq!(written(0.0), "0ns");
+        assert_eq!(written(-0.002), "-2ms");
+    }
+
+    #[test]
+    fn parse_units() {
+        assert_parsed("1.5s", 1.5);
+        assert_parsed("340ms", 0.34);
+        assert_parsed("12.5µs", 12.5e-6);
+        assert_parsed("7ns", 7e-9);
+    }
+
+    #[test]
+    fn parse_rejects_other_text() {
+        assert_eq!(parse_debug_duration("s"), None);
+        assert_eq!(parse_debug_duration("ms"), None);
+        assert_eq!(parse_debug_duration("1e3s"), None);
+        assert_eq!(parse_debug_duration("-1s"), None);
+        assert_eq!(parse_debug_duration("items"), None);
+        assert_eq!(parse_debug_duration("12"), None);
+    }
+}
@@ -0,0 +1,68 @@
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/// A cue to draw attention to ERROR events, see [`MakeWebConsoleWriter::with_error_alert`].
+///
+/// The alerts are meant for development, to notice errors while the console is not in view. If the needed API is not
+/// available, e.g. in a worker, the alert is silently skipped.
+///
+/// [`MakeWebConsoleWriter::with_error_alert`]: crate::MakeWebConsoleWriter::with_error_alert
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorAlert {
+    /// No alert, the default.
+    #[default]
+    None,
+    /// Flash a warning sign in the title of the document for a few seconds.
+    TitleFlash,
+    /// Play a short beep with the Web Audio API.
+    ///
+    /// Browsers only allow playing audio after the user interacted with the page, before that, the beep is muted.
+    Beep,
+}
+
+impl ErrorAlert {
+    pub(crate) fn trigger(self) {
+        match self {
+            ErrorAlert::None => {}
+            ErrorAlert::TitleFlash => flash_title(),
+            ErrorAlert::Beep => beep(),
+        }
+    }
+}
+
+#[wasm_bindgen(inline_js = r#"
+let flashing = false;
+export function flash_title() {
+    try {
+        if (flashing || typeof document === "undefined") return;
+        flashing = true;
+        const title = document.title;
+        let count = 0;
+        const timer = setInterval(() => {
+            count += 1;
+            document.title = count % 2 === 1 ? "⚠ " + title : title;
+            if (count >= 6) {
+                clearInterval(timer);
+                flashing = false;
+            }
+        }, 500);
+    } catch (e) {}
+}
+export function beep() {
+    try {
+        const AudioContext = globalThis.AudioContext || globalThis.webkitAudioContext;
+        if (!AudioContext) return;
+        const context = new AudioContext();
+        const oscillator = context.createOscillator();
+        oscillator.frequency.value = 880;
+        oscillator.connect(context.destination);
+        oscillator.onended = () => context.close();
+        oscillator.start();
+        oscillator.stop(context.currentTime + 0.15);
+    } catch (e) {}
+}
+"#)]
+extern "C" {
+    // Both degrade to doing nothing if the respective API is missing.
+    fn flash_title();
+    fn beep();
+}
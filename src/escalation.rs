@@ -0,0 +1,79 @@
+use std::{cell::Cell, marker::PhantomData};
+
+use tracing_core::{Event, Level, Subscriber};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+thread_local! {
+    // Whether the event currently being written on this thread is escalated.
+    static ESCALATE: Cell<bool> = Cell::new(false);
+}
+
+/// A [`Layer`] escalating the console output of events within spans in which an error occurred.
+///
+/// Once an ERROR event fires within a span, all further events within that span, or any span enclosing it, are
+/// logged one level more severe by a [`MakeWebConsoleWriter`], e.g. DEBUG events with `console.info` and WARN
+/// events with `console.error`. This makes verbose output from an operation that went wrong stand out, while the
+/// filtering and formatting of the events are unaffected. This is a companion layer, since a [`MakeWriter`] does not
+/// have access to the span context of the event it writes. It does not produce any output by itself.
+///
+/// # Note
+///
+/// The layer has to see each event before the fmt layer writes it, so it must be added to the registry *before* the
+/// fmt layer, see [`EventStyleLayer`](crate::EventStyleLayer) for an example.
+///
+/// [`MakeWebConsoleWriter`]: crate::MakeWebConsoleWriter
+/// [`MakeWriter`]: tracing_subscriber::fmt::MakeWriter
+pub struct EscalationLayer<S> {
+    _inner: PhantomData<fn(S)>,
+}
+
+/// Construct a new layer escalating the console output of events within spans in which an error occurred.
+pub fn escalation_layer<S>() -> EscalationLayer<S>
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    EscalationLayer {
+        _inner: PhantomData,
+    }
+}
+
+// Stored in the extensions of spans within which an ERROR event fired.
+struct ErrorSeen;
+
+impl<S> Layer<S> for EscalationLayer<S>
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut escalate = false;
+        if let Some(scope) = ctx.event_scope(event) {
+            if *event.metadata().level() == Level::ERROR {
+                for span in scope {
+                    span.extensions_mut().replace(ErrorSeen);
+                }
+            } else {
+                escalate = scope
+                    .into_iter()
+                    .any(|span| span.extensions().get::<ErrorSeen>().is_some());
+            }
+        }
+        // Always overwrite, so that an event that is not written does not affect the next one
+        ESCALATE.with(|current| current.set(escalate));
+    }
+}
+
+/// The level to dispatch the event currently being written with, one level more severe if it is escalated.
+pub(crate) fn escalated_level(level: Level) -> Level {
+    if !ESCALATE.with(|current| current.replace(false)) {
+        return level;
+    }
+    if level == Level::TRACE {
+        Level::DEBUG
+    } else if level == Level::DEBUG {
+        Level::INFO
+    } else if level == Level::INFO {
+        Level::WARN
+    } else {
+        Level::ERROR
+    }
+}
@@ -0,0 +1,96 @@
+use std::{cell::RefCell, fmt, marker::PhantomData};
+
+use tracing_core::{
+    field::{Field, Visit},
+    Event, Subscriber,
+};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+thread_local! {
+    // The style of the event currently being written on this thread, if it has one.
+    static EVENT_STYLE: RefCell<Option<String>> = RefCell::new(None);
+}
+
+/// A [`Layer`] picking up a custom CSS style for the console line of an event from one of its fields.
+///
+/// Events with a `style` field, e.g. `tracing::warn!(style = "color: red; font-size: 16px", "disk almost full")`,
+/// are then shown in that style by a [`MakeWebConsoleWriter`] with a pretty label, regardless of their level. This
+/// is a companion layer, since a [`MakeWriter`] does not have access to the fields of the event it writes. It does
+/// not produce any output by itself.
+///
+/// # Note
+///
+/// The layer has to see each event before the fmt layer writes it, so it must be added to the registry *before* the
+/// fmt layer:
+///
+/// ```rust, no_run
+/// # use tracing_subscriber::prelude::*;
+/// tracing_subscriber::registry()
+///     .with(tracing_web::event_style_layer())
+///     .with(tracing_subscriber::fmt::layer().with_writer(tracing_web::MakeWebConsoleWriter::new().with_pretty_level()))
+///     .init();
+/// ```
+///
+/// [`MakeWebConsoleWriter`]: crate::MakeWebConsoleWriter
+/// [`MakeWriter`]: tracing_subscriber::fmt::MakeWriter
+pub struct EventStyleLayer<S> {
+    field: &'static str,
+    _inner: PhantomData<fn(S)>,
+}
+
+/// Construct a new layer picking up the console style of events from their `style` field.
+pub fn event_style_layer<S>() -> EventStyleLayer<S>
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    EventStyleLayer {
+        field: "style",
+        _inner: PhantomData,
+    }
+}
+
+impl<S> EventStyleLayer<S> {
+    /// Pick up the style from a field with a different name than `style`.
+    pub fn with_field(mut self, field: &'static str) -> Self {
+        self.field = field;
+        self
+    }
+}
+
+struct StyleVisitor {
+    field: &'static str,
+    style: Option<String>,
+}
+
+impl Visit for StyleVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == self.field {
+            self.style = Some(value.to_owned());
+        }
+    }
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == self.field {
+            self.style = Some(format!("{:?}", value));
+        }
+    }
+}
+
+impl<S> Layer<S> for EventStyleLayer<S>
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = StyleVisitor {
+            field: self.field,
+            style: None,
+        };
+        event.record(&mut visitor);
+        // Always overwrite, so that the style of an event that is not written does not apply to the next one
+        EVENT_STYLE.with(|style| *style.borrow_mut() = visitor.style);
+    }
+}
+
+/// Take the style of the event currently being written, if it has one.
+pub(crate) fn take_event_style() -> Option<String> {
+    EVENT_STYLE.with(|style| style.borrow_mut().take())
+}
@@ -0,0 +1,31 @@
+use std::cell::RefCell;
+
+thread_local! {
+    // Values passed to `gauge`, waiting to be shown by the next console line on this thread.
+    static GAUGES: RefCell<Vec<(&'static str, f64)>> = RefCell::new(Vec::new());
+}
+
+/// Show a ratio in the console as a small, inline bar chart.
+///
+/// Use this helper as the value of a field holding a ratio between `0.0` and `1.0`, such as a percentage of progress
+/// or a cache hit rate. It returns the value unchanged, to be recorded as usual, and additionally sets it aside. The
+/// next line written by a [`ConsoleWriter`] with a pretty label on the same thread then shows a bar of proportional
+/// width after the message, labelled with `name`. Values outside of the range are clamped for the bar.
+///
+/// ```rust, ignore
+/// tracing::info!(hit_rate = tracing_web::gauge("hit_rate", hits / total), "cache stats");
+/// ```
+///
+/// Without a pretty label, i.e. with plain text output, the bar is not shown. The same constraints as for
+/// [`js_field`](crate::js_field) apply.
+///
+/// [`ConsoleWriter`]: crate::ConsoleWriter
+pub fn gauge(name: &'static str, ratio: f64) -> f64 {
+    GAUGES.with(|gauges| gauges.borrow_mut().push((name, ratio)));
+    ratio
+}
+
+/// Take the values set aside by [`gauge`] since the last call.
+pub(crate) fn take_gauges() -> Vec<(&'static str, f64)> {
+    GAUGES.with(|gauges| std::mem::take(&mut *gauges.borrow_mut()))
+}
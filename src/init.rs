@@ -0,0 +1,37 @@
+use tracing_subscriber::{
+    filter::{EnvFilter, ParseError},
+    fmt::format::Pretty,
+    prelude::*,
+};
+
+use crate::{performance_layer, MakeWebConsoleWriter};
+
+/// Install a subscriber writing to the console and the Performance API, filtered by the given directives.
+///
+/// The `directives` use the syntax of [`EnvFilter`], e.g. `"info,my_app=trace"`. Since there is no `RUST_LOG`
+/// environment variable in the browser, they can instead be taken from a query parameter or a build constant. This
+/// is equivalent to the setup in the crate level example, with the filter added:
+///
+/// ```rust, no_run
+/// tracing_web::init_with_filter("info,my_app=debug").expect("invalid filter directives");
+/// ```
+///
+/// Returns an error if the directives can not be parsed. Panics if a global default subscriber has already been set,
+/// like [`SubscriberInitExt::init`]. Requires the `env-filter` feature.
+///
+/// [`SubscriberInitExt::init`]: tracing_subscriber::util::SubscriberInitExt::init
+pub fn init_with_filter(directives: &str) -> Result<(), ParseError> {
+    let filter = EnvFilter::try_new(directives)?;
+    let fmt_layer = tracing_subscriber::fmt::layer()
+        .with_ansi(false)
+        .without_time()
+        .with_writer(MakeWebConsoleWriter::new());
+    let perf_layer = performance_layer().with_details_from_fields(Pretty::default());
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt_layer)
+        .with(perf_layer)
+        .init();
+    Ok(())
+}
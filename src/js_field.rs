@@ -0,0 +1,58 @@
+use std::cell::RefCell;
+
+use wasm_bindgen::JsValue;
+
+thread_local! {
+    // Values passed to `js_field`, waiting to be logged by the next console line on this thread.
+    static STASHED: RefCell<Vec<(&'static str, JsValue)>> = RefCell::new(Vec::new());
+}
+
+/// Pass a [`JsValue`] along with an event, to be logged as an inspectable object in the console.
+///
+/// Tracing fields can not hold a [`JsValue`], such as a DOM event or a fetch `Response`. Instead, use this helper as
+/// the value of a field. It sets the value aside, and returns a placeholder to record in its place. The next line
+/// written by a [`ConsoleWriter`] on the same thread then passes the value as an additional `%o` argument to the
+/// console, labelled with `name`.
+///
+/// ```rust, ignore
+/// tracing::info!(event = tracing_web::js_field("event", event.into()), "clicked");
+/// ```
+///
+/// # Constraints
+///
+/// - Only use this directly in the field list of an event that is written to the console. Field values are only
+///   evaluated when the event is enabled, but if the event is then not written by a [`ConsoleWriter`], e.g. because
+///   it is filtered for that layer only, the value is logged with the next line written instead.
+/// - The value is held in a thread-local, and is logged by a writer on the same thread only.
+/// - Other layers only see the returned placeholder.
+///
+/// [`ConsoleWriter`]: crate::ConsoleWriter
+pub fn js_field(name: &'static str, value: JsValue) -> &'static str {
+    STASHED.with(|stashed| stashed.borrow_mut().push((name, value)));
+    "[js]"
+}
+
+/// Pass a serializable value along with an event, to be logged as an inspectable object in the console.
+///
+/// The value is converted into a JS object with [`serde_wasm_bindgen`] and then passed on as with [`js_field`], with
+/// the same constraints. This only works for values wrapped in this helper. Values recorded with the plain field
+/// syntax of `tracing`, such as `?value`, are formatted as text as usual.
+///
+/// ```rust, ignore
+/// tracing::info!(state = tracing_web::serde_field("state", &state), "updated");
+/// ```
+///
+/// If the value can not be serialized, nothing is logged in addition and a different placeholder is returned.
+/// Requires the `serde` feature.
+#[cfg(feature = "serde")]
+pub fn serde_field<T: serde::Serialize + ?Sized>(name: &'static str, value: &T) -> &'static str {
+    match serde_wasm_bindgen::to_value(value) {
+        Ok(value) => js_field(name, value),
+        Err(_) => "[js: not serializable]",
+    }
+}
+
+/// Take the values set aside by [`js_field`] since the last call.
+pub(crate) fn take_js_fields() -> Vec<(&'static str, JsValue)> {
+    STASHED.with(|stashed| std::mem::take(&mut *stashed.borrow_mut()))
+}
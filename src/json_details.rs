@@ -0,0 +1,154 @@
+use std::fmt::{self, Write};
+
+use tracing_core::{
+    field::{Field, Visit},
+    span,
+};
+use tracing_subscriber::registry::{Extensions, ExtensionsMut};
+
+use crate::FormatSpan;
+
+/// A [`FormatSpan`] attaching the fields of spans as a JSON object, e.g. `{"id":42,"path":"/user"}`.
+///
+/// Unlike the formatters used with [`with_details_from_fields`], the details can be parsed with `JSON.parse` by
+/// tooling. Numbers and booleans are kept as such, all other values are recorded as strings, using their
+/// [`Display`](fmt::Display) representation for strings and their [`Debug`](fmt::Debug) representation otherwise.
+///
+/// ```rust, no_run
+/// use tracing_web::{performance_layer, JsonDetails};
+/// # use tracing_subscriber::Registry;
+///
+/// let perf_layer = performance_layer::<Registry>().with_details(JsonDetails::new());
+/// ```
+///
+/// The details are passed to the Performance API as a string, so that a `PerformanceObserver` can parse them:
+///
+/// ```js
+/// new PerformanceObserver((list) => {
+///     for (const entry of list.getEntries()) {
+///         const json = typeof entry.detail === "string" ? entry.detail : entry.detail?.fields;
+///         const fields = json ? JSON.parse(json) : {};
+///     }
+/// }).observe({ entryTypes: ["mark", "measure"] });
+/// ```
+///
+/// If the layer uses structured details, e.g. with [`with_parent_info`], the JSON string is found in the `fields`
+/// property of the detail object instead. Integers beyond 64 bits are recorded as
+/// strings, since they can not be represented exactly by JS numbers.
+///
+/// [`with_parent_info`]: crate::PerformanceEventsLayer::with_parent_info
+/// [`with_details_from_fields`]: crate::PerformanceEventsLayer::with_details_from_fields
+#[derive(Debug, Clone, Default)]
+pub struct JsonDetails {
+    _private: (),
+}
+
+impl JsonDetails {
+    /// Create a new JSON formatter for details.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+// The fields of a span as JSON values, and the serialized object, stored in its extensions.
+#[derive(Default)]
+struct JsonFields {
+    values: Vec<(&'static str, String)>,
+    json: String,
+}
+
+impl JsonFields {
+    fn set(&mut self, name: &'static str, value: String) {
+        match self
+            .values
+            .iter_mut()
+            .find(|(existing, _)| *existing == name)
+        {
+            Some((_, existing)) => *existing = value,
+            None => self.values.push((name, value)),
+        }
+    }
+    fn serialize(&mut self) {
+        self.json.clear();
+        self.json.push('{');
+        for (idx, (name, value)) in self.values.iter().enumerate() {
+            if idx > 0 {
+                self.json.push(',');
+            }
+            write_json_string(&mut self.json, name);
+            self.json.push(':');
+            self.json.push_str(value);
+        }
+        self.json.push('}');
+    }
+}
+
+fn write_json_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    write_json_string(&mut out, value);
+    out
+}
+
+impl Visit for JsonFields {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        // JSON has no representation for NaN and the infinities
+        let value = if value.is_finite() {
+            value.to_string()
+        } else {
+            "null".to_owned()
+        };
+        self.set(field.name(), value);
+    }
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.set(field.name(), value.to_string());
+    }
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.set(field.name(), value.to_string());
+    }
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.set(field.name(), value.to_string());
+    }
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.set(field.name(), json_string(value));
+    }
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.set(field.name(), json_string(&format!("{:?}", value)));
+    }
+}
+
+impl FormatSpan for JsonDetails {
+    fn find_details<'ext>(&self, ext: &'ext Extensions<'_>) -> Option<&'ext str> {
+        let fields = ext.get::<JsonFields>()?;
+        Some(&fields.json)
+    }
+    fn add_details(&self, ext: &mut ExtensionsMut<'_>, attrs: &span::Attributes<'_>) {
+        let mut fields = JsonFields::default();
+        attrs.record(&mut fields);
+        fields.serialize();
+        ext.insert(fields);
+    }
+    fn record_values(&self, ext: &mut ExtensionsMut<'_>, values: &span::Record<'_>) {
+        if let Some(fields) = ext.get_mut::<JsonFields>() {
+            values.record(fields);
+            fields.serialize();
+        }
+    }
+}
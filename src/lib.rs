@@ -27,9 +27,62 @@
     elided_lifetimes_in_paths
 )]
 
+mod ansi;
 mod performance_layer;
 pub use performance_layer::{
-    performance_layer, FormatSpan, FormatSpanFromFields, PerformanceEventsLayer,
+    current_propagation_value, is_perf_sampled, mark, mark_at, measure, performance_available,
+    performance_layer, performance_now, span_id_token, DetailKeys, FormatSpan,
+    FormatSpanFromFields, Measure, OpenSpans, PerfBackend, PerfSampled, PerformanceEventsLayer,
+    PerformanceLayerBuilder, WebPerformance,
 };
+mod bytes_field;
+pub use bytes_field::{bytes_field, BytesFormat};
+mod compact_format;
+pub use compact_format::CompactConsoleFormat;
+mod console_group;
+pub use console_group::{close_app_group, console_group_layer, open_app_group, ConsoleGroupLayer};
+mod console_time;
+pub use console_time::{console_time_layer, ConsoleTimeLayer};
 mod console_writer;
-pub use console_writer::{ConsoleWriter, MakeConsoleWriter, MakeWebConsoleWriter};
+pub use console_writer::{
+    ConsoleMethod, ConsoleWriter, ConsoleWriterConfig, LevelSet, MakeConsoleWriter,
+    MakeWebConsoleWriter, PrettyLabel, PrettyToggle,
+};
+mod duration_fields;
+pub use duration_fields::DurationFields;
+mod error_alert;
+pub use error_alert::ErrorAlert;
+mod escalation;
+pub use escalation::{escalation_layer, EscalationLayer};
+mod event_style;
+pub use event_style::{event_style_layer, EventStyleLayer};
+mod gauge;
+pub use gauge::gauge;
+#[cfg(feature = "env-filter")]
+mod init;
+#[cfg(feature = "env-filter")]
+pub use init::init_with_filter;
+mod js_field;
+pub use js_field::js_field;
+#[cfg(feature = "serde")]
+pub use js_field::serde_field;
+mod json_details;
+pub use json_details::JsonDetails;
+mod max_fields;
+pub use max_fields::{MaxFields, MaxFieldsVisitor};
+mod panic_hook;
+pub use panic_hook::set_panic_hook;
+mod quiet;
+pub use quiet::{quiet_layer, QuietLayer};
+mod sorted_fields;
+pub use sorted_fields::SortedFields;
+mod span_depth;
+pub use span_depth::{span_depth_layer, BreadcrumbOrder, SpanDepthLayer};
+mod span_summary;
+pub use span_summary::{console_span_summary_layer, ConsoleSpanSummaryLayer};
+mod structured_layer;
+pub use structured_layer::{structured_console_layer, StructuredConsoleLayer};
+mod trace_export;
+pub use trace_export::export_performance_trace;
+mod web_trace;
+pub use web_trace::{web_trace_layer, WebTraceLayer};
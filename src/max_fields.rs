@@ -0,0 +1,134 @@
+use std::fmt;
+
+use tracing_core::field::{Field, Visit};
+use tracing_subscriber::field::{MakeVisitor, VisitFmt, VisitOutput};
+
+/// A field formatter wrapping another, which writes at most a given number of fields.
+///
+/// Fields after the first `max` are skipped, and the number of skipped fields is appended as `, …(3 more)`. The
+/// message of events does not count towards the limit. This keeps the output of spans and events with many fields
+/// legible, both in the console and in the details of performance events:
+///
+/// ```rust, no_run
+/// use tracing_subscriber::fmt::format::DefaultFields;
+/// use tracing_web::{performance_layer, MaxFields};
+/// # use tracing_subscriber::Registry;
+///
+/// let fmt_layer =
+///     tracing_subscriber::fmt::layer::<Registry>().fmt_fields(MaxFields::new(DefaultFields::new(), 5));
+/// let perf_layer =
+///     performance_layer::<Registry>().with_details_from_fields(MaxFields::new(DefaultFields::new(), 5));
+/// ```
+///
+/// This works with the field formatters of `tracing-subscriber`, such as [`DefaultFields`] and [`Pretty`], which
+/// are built on [`MakeVisitor`]. Since the limit applies each time fields are formatted, fields recorded later, e.g.
+/// with [`Span::record`], are limited separately. See [`CompactConsoleFormat::with_max_fields`] for the compact
+/// event format.
+///
+/// [`DefaultFields`]: tracing_subscriber::fmt::format::DefaultFields
+/// [`Pretty`]: tracing_subscriber::fmt::format::Pretty
+/// [`Span::record`]: https://docs.rs/tracing/latest/tracing/struct.Span.html#method.record
+/// [`CompactConsoleFormat::with_max_fields`]: crate::CompactConsoleFormat::with_max_fields
+#[derive(Debug, Clone)]
+pub struct MaxFields<N> {
+    inner: N,
+    max: usize,
+}
+
+impl<N> MaxFields<N> {
+    /// Wrap the field formatter `inner`, to write at most `max` fields.
+    pub fn new(inner: N, max: usize) -> Self {
+        Self { inner, max }
+    }
+}
+
+impl<T, N> MakeVisitor<T> for MaxFields<N>
+where
+    N: MakeVisitor<T>,
+{
+    type Visitor = MaxFieldsVisitor<N::Visitor>;
+
+    fn make_visitor(&self, target: T) -> Self::Visitor {
+        MaxFieldsVisitor {
+            inner: self.inner.make_visitor(target),
+            remaining: self.max,
+            skipped: 0,
+        }
+    }
+}
+
+/// The visitor of [`MaxFields`], forwarding the first fields to the visitor of the wrapped formatter.
+#[derive(Debug)]
+pub struct MaxFieldsVisitor<V> {
+    inner: V,
+    remaining: usize,
+    skipped: usize,
+}
+
+impl<V> MaxFieldsVisitor<V> {
+    // Whether to forward the field, counting it towards the limit.
+    fn admit(&mut self, field: &Field) -> bool {
+        if field.name() == "message" {
+            return true;
+        }
+        if self.remaining == 0 {
+            self.skipped += 1;
+            return false;
+        }
+        self.remaining -= 1;
+        true
+    }
+}
+
+impl<V: Visit> Visit for MaxFieldsVisitor<V> {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        if self.admit(field) {
+            self.inner.record_f64(field, value);
+        }
+    }
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        if self.admit(field) {
+            self.inner.record_i64(field, value);
+        }
+    }
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        if self.admit(field) {
+            self.inner.record_u64(field, value);
+        }
+    }
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        if self.admit(field) {
+            self.inner.record_bool(field, value);
+        }
+    }
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if self.admit(field) {
+            self.inner.record_str(field, value);
+        }
+    }
+    fn record_error(&mut self, field: &Field, value: &(dyn std::error::Error + 'static)) {
+        if self.admit(field) {
+            self.inner.record_error(field, value);
+        }
+    }
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if self.admit(field) {
+            self.inner.record_debug(field, value);
+        }
+    }
+}
+
+impl<V: VisitFmt> VisitOutput<fmt::Result> for MaxFieldsVisitor<V> {
+    fn finish(mut self) -> fmt::Result {
+        if self.skipped > 0 {
+            write!(self.inner.writer(), ", …({} more)", self.skipped)?;
+        }
+        self.inner.finish()
+    }
+}
+
+impl<V: VisitFmt> VisitFmt for MaxFieldsVisitor<V> {
+    fn writer(&mut self) -> &mut dyn fmt::Write {
+        self.inner.writer()
+    }
+}
@@ -0,0 +1,33 @@
+/// Install a panic hook that reports panics as ERROR events through the configured subscriber.
+///
+/// The default panic hook writes the message to stderr, which is not shown anywhere in the browser. With this hook,
+/// the panic message and location are emitted with `tracing::error!` instead, and so show up formatted like all
+/// other events, within the context of the current span. Call this once at startup, after setting up the subscriber.
+///
+/// ```rust, no_run
+/// tracing_web::set_panic_hook();
+/// ```
+pub fn set_panic_hook() {
+    std::panic::set_hook(Box::new(|info| {
+        let payload = info.payload();
+        let message = if let Some(message) = payload.downcast_ref::<&str>() {
+            *message
+        } else if let Some(message) = payload.downcast_ref::<String>() {
+            message.as_str()
+        } else {
+            "Box<dyn Any>"
+        };
+        match info.location() {
+            Some(location) => {
+                let location = format!(
+                    "{}:{}:{}",
+                    location.file(),
+                    location.line(),
+                    location.column()
+                );
+                tracing::error!(target: "panic", location = %location, "panicked: {message}")
+            }
+            None => tracing::error!(target: "panic", "panicked: {message}"),
+        }
+    }));
+}
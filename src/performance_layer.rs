@@ -1,13 +1,24 @@
-use std::marker::PhantomData;
+use std::{
+    cell::{Cell, OnceCell, RefCell},
+    fmt,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
 
-use js_sys::{JsString, Object, Reflect};
-use tracing_core::{span, Subscriber};
+use js_sys::{Array, JsString, Object, Reflect};
+use tracing_core::{
+    field::{Field, Visit},
+    span, Event, Subscriber,
+};
 use tracing_subscriber::{
     field::RecordFields,
     fmt::{FormatFields, FormattedFields},
     layer::Context,
     registry::{Extensions, ExtensionsMut, LookupSpan, SpanRef},
-    Layer,
+    Layer, Registry,
 };
 use wasm_bindgen::{prelude::wasm_bindgen, JsCast, JsValue};
 
@@ -16,9 +27,14 @@ extern "C" {
     #[wasm_bindgen(js_name = _fakeGlobal)]
     type Global;
     #[wasm_bindgen()]
-    type Performance;
+    pub(crate) type Performance;
     #[wasm_bindgen(static_method_of = Global, js_class = "globalThis", getter)]
     fn performance() -> Performance;
+    #[wasm_bindgen(method)]
+    fn now(this: &Performance) -> f64;
+    // Non-standard, only available in Chromium based browsers
+    #[wasm_bindgen(method, getter)]
+    fn memory(this: &Performance) -> JsValue;
     #[wasm_bindgen(method, catch, js_name = "mark")]
     fn do_mark(this: &Performance, name: &str) -> Result<(), JsValue>;
     #[wasm_bindgen(method, catch, js_name = "mark")]
@@ -35,21 +51,41 @@ extern "C" {
         end: &str,
     ) -> Result<(), JsValue>;
     #[wasm_bindgen(method, catch, js_name = "measure")]
+    fn do_measure_with_start_mark(
+        this: &Performance,
+        name: &str,
+        start: &str,
+    ) -> Result<(), JsValue>;
+    #[wasm_bindgen(method, catch, js_name = "measure")]
     fn do_measure_with_details(
         this: &Performance,
         name: &str,
         details: &JsValue,
     ) -> Result<(), JsValue>;
+    #[wasm_bindgen(method, js_name = "getEntriesByType")]
+    pub(crate) fn get_entries_by_type(this: &Performance, entry_type: &str) -> Array;
+    #[wasm_bindgen(method, js_name = "clearMarks")]
+    fn clear_marks(this: &Performance, name: &str);
+    #[wasm_bindgen(method, js_name = "clearMeasures")]
+    fn clear_measures(this: &Performance, name: &str);
 }
 
 impl Performance {
     fn mark(&self, name: &str) -> Result<(), JsValue> {
         self.do_mark(name)
     }
-    fn mark_detailed(&self, name: &str, details: &str) -> Result<(), JsValue> {
+    fn mark_detailed(&self, name: &str, details: &JsValue) -> Result<(), JsValue> {
         let details_obj = Object::create(JsValue::NULL.unchecked_ref::<Object>());
         let detail_prop = JsString::from(wasm_bindgen::intern("detail"));
-        Reflect::set(&details_obj, &detail_prop, &JsValue::from(details)).unwrap();
+        Reflect::set(&details_obj, &detail_prop, details).unwrap();
+        self.do_mark_with_details(name, &details_obj)
+    }
+    fn mark_at(&self, name: &str, start_time: f64, details: &JsValue) -> Result<(), JsValue> {
+        let details_obj = Object::create(JsValue::NULL.unchecked_ref::<Object>());
+        let detail_prop = JsString::from(wasm_bindgen::intern("detail"));
+        let start_time_prop = JsString::from(wasm_bindgen::intern("startTime"));
+        Reflect::set(&details_obj, &detail_prop, details).unwrap();
+        Reflect::set(&details_obj, &start_time_prop, &JsValue::from(start_time)).unwrap();
         self.do_mark_with_details(name, &details_obj)
     }
     fn measure(&self, name: &str, start: &str, end: &str) -> Result<(), JsValue> {
@@ -60,25 +96,272 @@ impl Performance {
         name: &str,
         start: &str,
         end: &str,
-        details: &str,
+        details: &JsValue,
+    ) -> Result<(), JsValue> {
+        let details_obj = Object::create(JsValue::NULL.unchecked_ref::<Object>());
+        let detail_prop = JsString::from(wasm_bindgen::intern("detail"));
+        let start_prop = JsString::from(wasm_bindgen::intern("start"));
+        let end_prop = JsString::from(wasm_bindgen::intern("end"));
+        Reflect::set(&details_obj, &detail_prop, details).unwrap();
+        Reflect::set(&details_obj, &start_prop, &JsValue::from(start)).unwrap();
+        Reflect::set(&details_obj, &end_prop, &JsValue::from(end)).unwrap();
+        self.do_measure_with_details(name, &details_obj)
+    }
+    fn measure_since_detailed(
+        &self,
+        name: &str,
+        start: &str,
+        details: &JsValue,
+    ) -> Result<(), JsValue> {
+        let details_obj = Object::create(JsValue::NULL.unchecked_ref::<Object>());
+        let detail_prop = JsString::from(wasm_bindgen::intern("detail"));
+        let start_prop = JsString::from(wasm_bindgen::intern("start"));
+        Reflect::set(&details_obj, &detail_prop, details).unwrap();
+        Reflect::set(&details_obj, &start_prop, &JsValue::from(start)).unwrap();
+        self.do_measure_with_details(name, &details_obj)
+    }
+    fn measure_between(
+        &self,
+        name: &str,
+        start: f64,
+        end: f64,
+        details: &JsValue,
     ) -> Result<(), JsValue> {
         let details_obj = Object::create(JsValue::NULL.unchecked_ref::<Object>());
         let detail_prop = JsString::from(wasm_bindgen::intern("detail"));
         let start_prop = JsString::from(wasm_bindgen::intern("start"));
         let end_prop = JsString::from(wasm_bindgen::intern("end"));
-        Reflect::set(&details_obj, &detail_prop, &JsValue::from(details)).unwrap();
+        Reflect::set(&details_obj, &detail_prop, details).unwrap();
         Reflect::set(&details_obj, &start_prop, &JsValue::from(start)).unwrap();
         Reflect::set(&details_obj, &end_prop, &JsValue::from(end)).unwrap();
         self.do_measure_with_details(name, &details_obj)
     }
+    // Names of the entries of the given type starting with `prefix`, each name only once.
+    fn entry_names_with_prefix(&self, entry_type: &str, prefix: &str) -> Vec<String> {
+        let name_prop = JsString::from(wasm_bindgen::intern("name"));
+        let entries = self.get_entries_by_type(entry_type);
+        let mut names = vec![];
+        for i in 0..entries.length() {
+            let name = Reflect::get(&entries.get(i), &name_prop)
+                .ok()
+                .and_then(|name| name.as_string());
+            if let Some(name) = name {
+                if name.starts_with(prefix) && !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        names
+    }
 }
 
 thread_local! {
-    static PERF: Performance = {
-        let performance = Global::performance();
-        assert!(!performance.is_undefined(), "browser seems to not support the Performance API");
-        performance
-    };
+    // Looked up lazily on first use, `None` if the Performance API is not supported.
+    static PERF: OnceCell<Option<Performance>> = OnceCell::new();
+}
+
+pub(crate) fn with_performance<R>(f: impl FnOnce(&Performance) -> R) -> Option<R> {
+    PERF.with(|perf| {
+        perf.get_or_init(|| {
+            let performance = Global::performance();
+            if performance.is_undefined() {
+                None
+            } else {
+                Some(performance)
+            }
+        })
+        .as_ref()
+        .map(f)
+    })
+}
+
+/// Check if the [`performance`] API is available in the current global scope.
+///
+/// When it is not, [`PerformanceEventsLayer`] silently skips emitting any performance events.
+///
+/// [`performance`]: https://developer.mozilla.org/en-US/docs/Web/API/Performance
+pub fn performance_available() -> bool {
+    with_performance(|_| ()).is_some()
+}
+
+/// The current high resolution time from `performance.now()`, in milliseconds, to be used with [`measure`].
+///
+/// Returns `None` if the Performance API is not available.
+pub fn performance_now() -> Option<f64> {
+    with_performance(|p| p.now())
+}
+
+/// Emit an ad-hoc performance mark, for a point in time not modeled as a tracing event.
+///
+/// To group the mark with the entries of a [`PerformanceEventsLayer`], follow its naming convention and prefix the
+/// name with the namespace, e.g. `my-app:hydrated`. The `detail`, if any, is attached as is. Does nothing if the
+/// Performance API is not available. Errors thrown by `performance.mark` are returned.
+pub fn mark(name: &str, detail: Option<&JsValue>) -> Result<(), JsValue> {
+    with_performance(|p| match detail {
+        Some(detail) => p.mark_detailed(name, detail),
+        None => p.mark(name),
+    })
+    .unwrap_or(Ok(()))
+}
+
+/// Emit an ad-hoc performance mark at the given `start_time`, e.g. for an event that is only learned about later.
+///
+/// The `start_time` is a high resolution time as obtained from [`performance_now`], and can lie in the past. To
+/// place a timestamp from elsewhere, such as a server response, subtract `performance.timeOrigin` from it first. See
+/// [`mark`] for the naming convention. Does nothing if the Performance API is not available. Errors thrown by
+/// `performance.mark`, e.g. for a negative `start_time`, are returned.
+pub fn mark_at(name: &str, start_time: f64, detail: Option<&JsValue>) -> Result<(), JsValue> {
+    with_performance(|p| p.mark_at(name, start_time, detail.unwrap_or(&JsValue::UNDEFINED)))
+        .unwrap_or(Ok(()))
+}
+
+/// Emit an ad-hoc performance measure, for an operation not modeled as a tracing span.
+///
+/// The measure spans from `start` to `end`, as obtained from [`performance_now`]. See [`mark`] for the naming
+/// convention. Does nothing if the Performance API is not available. Errors thrown by `performance.measure` are
+/// returned.
+pub fn measure(name: &str, start: f64, end: f64, detail: Option<&JsValue>) -> Result<(), JsValue> {
+    with_performance(|p| p.measure_between(name, start, end, detail.unwrap_or(&JsValue::UNDEFINED)))
+        .unwrap_or(Ok(()))
+}
+
+/// The operations of the Performance API used by a [`PerformanceEventsLayer`].
+///
+/// By default, the layer uses [`WebPerformance`], i.e. the [`performance`] object of the current global scope.
+/// Replace it with [`PerformanceEventsLayer::with_backend`], e.g. with a mock recording the names of marks and
+/// measures, to test instrumentation deterministically.
+///
+/// [`performance`]: https://developer.mozilla.org/en-US/docs/Web/API/Performance
+pub trait PerfBackend: Send + Sync + 'static {
+    /// The current high resolution time in milliseconds, or `None` if unavailable.
+    fn now(&self) -> Option<f64>;
+    /// Emit a mark, with an optional `detail`.
+    fn mark(&self, name: &str, detail: Option<&JsValue>) -> Result<(), JsValue>;
+    /// Emit a measure from the mark `start`, to the mark `end` or to now if `end` is `None`.
+    fn measure(
+        &self,
+        name: &str,
+        start: &str,
+        end: Option<&str>,
+        detail: Option<&JsValue>,
+    ) -> Result<(), JsValue>;
+    /// Emit a measure between two timestamps, as obtained from [`now`](Self::now).
+    fn measure_between(
+        &self,
+        name: &str,
+        start: f64,
+        end: f64,
+        detail: Option<&JsValue>,
+    ) -> Result<(), JsValue>;
+    /// Clear the marks and measures with a name starting with `prefix`.
+    fn clear(&self, prefix: &str);
+}
+
+/// The default [`PerfBackend`], using the [`performance`] object of the current global scope.
+///
+/// All operations do nothing if the Performance API is not available.
+///
+/// [`performance`]: https://developer.mozilla.org/en-US/docs/Web/API/Performance
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WebPerformance;
+
+impl PerfBackend for WebPerformance {
+    fn now(&self) -> Option<f64> {
+        performance_now()
+    }
+    fn mark(&self, name: &str, detail: Option<&JsValue>) -> Result<(), JsValue> {
+        mark(name, detail)
+    }
+    fn measure(
+        &self,
+        name: &str,
+        start: &str,
+        end: Option<&str>,
+        detail: Option<&JsValue>,
+    ) -> Result<(), JsValue> {
+        with_performance(|p| match (end, detail) {
+            (Some(end), Some(detail)) => p.measure_detailed(name, start, end, detail),
+            (Some(end), None) => p.measure(name, start, end),
+            (None, Some(detail)) => p.measure_since_detailed(name, start, detail),
+            (None, None) => p.do_measure_with_start_mark(name, start),
+        })
+        .unwrap_or(Ok(()))
+    }
+    fn measure_between(
+        &self,
+        name: &str,
+        start: f64,
+        end: f64,
+        detail: Option<&JsValue>,
+    ) -> Result<(), JsValue> {
+        measure(name, start, end, detail)
+    }
+    fn clear(&self, prefix: &str) {
+        with_performance(|p| {
+            for name in p.entry_names_with_prefix("mark", prefix) {
+                p.clear_marks(&name);
+            }
+            for name in p.entry_names_with_prefix("measure", prefix) {
+                p.clear_measures(&name);
+            }
+        });
+    }
+}
+
+/// The names of the properties of the details attached to performance events, see
+/// [`PerformanceEventsLayer::with_detail_keys`].
+///
+/// ```rust, no_run
+/// use tracing_web::{performance_layer, DetailKeys};
+/// # use tracing_subscriber::Registry;
+///
+/// let perf_layer = performance_layer::<Registry>().with_detail_keys(DetailKeys {
+///     fields: "attributes",
+///     ..DetailKeys::default()
+/// });
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DetailKeys {
+    /// The formatted fields of the span, `fields` by default.
+    pub fields: &'static str,
+    /// The sequence number, see [`PerformanceEventsLayer::with_sequence_numbers`], `seq` by default.
+    pub seq: &'static str,
+    /// The parent span, see [`PerformanceEventsLayer::with_parent_info`], `parent` by default.
+    pub parent: &'static str,
+    /// The category, see [`PerformanceEventsLayer::with_category_field`], `category` by default.
+    pub category: &'static str,
+    /// The build id, see [`PerformanceEventsLayer::with_build_id`], `build` by default.
+    pub build: &'static str,
+    /// The memory usage, see [`PerformanceEventsLayer::with_memory_sampling`], `memory` by default.
+    pub memory: &'static str,
+    /// The nesting lane, see [`PerformanceEventsLayer::with_lanes`], `lane` by default.
+    pub lane: &'static str,
+    /// The causal links, see [`PerformanceEventsLayer::with_follows_from`], `follows` by default.
+    pub follows: &'static str,
+    /// The span id token, see [`PerformanceEventsLayer::with_span_id_token`], `span` by default.
+    pub span: &'static str,
+    /// The busy time, see [`PerformanceEventsLayer::with_busy_time`], `busy` by default.
+    pub busy: &'static str,
+    /// The idle time, see [`PerformanceEventsLayer::with_busy_time`], `idle` by default.
+    pub idle: &'static str,
+}
+
+impl Default for DetailKeys {
+    fn default() -> Self {
+        Self {
+            fields: "fields",
+            seq: "seq",
+            parent: "parent",
+            category: "category",
+            build: "build",
+            memory: "memory",
+            lane: "lane",
+            follows: "follows",
+            span: "span",
+            busy: "busy",
+            idle: "idle",
+        }
+    }
 }
 
 /// A [`Layer`] that emits span enter, exit and events as [`performance`] marks.
@@ -86,9 +369,416 @@ thread_local! {
 /// [`performance`]: https://developer.mozilla.org/en-US/docs/Web/API/Performance
 pub struct PerformanceEventsLayer<S, N = ()> {
     fmt_details: N,
+    options: LayerOptions<S>,
     _inner: PhantomData<fn(S)>,
 }
 
+type DetailAugment<S> = Box<dyn Fn(&mut Object, &SpanRef<'_, S>) + Send + Sync>;
+
+// Options that are independent of the way details are formatted, and carried over in `with_details`.
+struct LayerOptions<S> {
+    namespace: Option<String>,
+    name_key: Option<&'static str>,
+    detail_augment: Option<DetailAugment<S>>,
+    busy_time: bool,
+    parent_info: bool,
+    sampling: Option<f64>,
+    sample_field: Option<&'static str>,
+    sample_seed: u64,
+    sequence: Option<Arc<AtomicU64>>,
+    create_marks: bool,
+    record_marks: bool,
+    emit_marks: bool,
+    duration_in_name: bool,
+    event_timestamps: bool,
+    event_marks: bool,
+    event_span_names: bool,
+    inherit_depth: usize,
+    open_spans: Option<OpenSpans>,
+    timestamp_measures: bool,
+    color_field: Option<&'static str>,
+    category_field: Option<&'static str>,
+    build_id: Option<String>,
+    propagation_field: Option<&'static str>,
+    memory_sampling: bool,
+    lanes: bool,
+    follows_from: bool,
+    span_id_token: bool,
+    nested_tracks: bool,
+    detail_keys: DetailKeys,
+    coalesce: bool,
+    measure_callback: Option<MeasureCallback>,
+    measure_sink: Option<MeasureSink>,
+    backend: Arc<dyn PerfBackend>,
+}
+
+type MeasureCallback = Box<dyn Fn(&str, f64, f64, Option<&str>) + Send + Sync>;
+type MeasureSink = Mutex<Box<dyn FnMut(Measure) + Send>>;
+
+/// A completed `span-measure`, as passed to [`PerformanceEventsLayer::with_measure_sink`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Measure {
+    /// The name of the measure, as in the Performance timeline.
+    pub name: String,
+    /// The start time in milliseconds, as obtained from `performance.now()`.
+    pub start: f64,
+    /// The duration in milliseconds.
+    pub duration: f64,
+    /// The details formatted by the [`FormatSpan`] of the layer, if any.
+    pub detail: Option<String>,
+}
+
+impl<S> Default for LayerOptions<S> {
+    fn default() -> Self {
+        Self {
+            namespace: None,
+            name_key: None,
+            detail_augment: None,
+            busy_time: false,
+            parent_info: false,
+            sampling: None,
+            sample_field: None,
+            sample_seed: 0,
+            sequence: None,
+            create_marks: false,
+            record_marks: true,
+            emit_marks: true,
+            duration_in_name: false,
+            event_timestamps: false,
+            event_marks: false,
+            event_span_names: false,
+            inherit_depth: 0,
+            open_spans: None,
+            timestamp_measures: false,
+            color_field: None,
+            category_field: None,
+            build_id: None,
+            propagation_field: None,
+            memory_sampling: false,
+            lanes: false,
+            follows_from: false,
+            span_id_token: false,
+            nested_tracks: false,
+            detail_keys: DetailKeys::default(),
+            coalesce: false,
+            measure_callback: None,
+            measure_sink: None,
+            backend: Arc::new(WebPerformance),
+        }
+    }
+}
+
+// The value of the field designated with `with_name_key`, captured when the span is created.
+struct NameKey(String);
+
+// Finds the value of a single field, formatted as a string.
+struct FieldValueVisitor {
+    field: &'static str,
+    value: Option<String>,
+}
+
+impl Visit for FieldValueVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == self.field {
+            self.value = Some(value.to_owned());
+        }
+    }
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == self.field {
+            self.value = Some(format!("{:?}", value));
+        }
+    }
+}
+
+/// A handle to emit a final measure for all spans that are currently entered, e.g. before the page is unloaded.
+///
+/// Obtained from [`PerformanceEventsLayer::open_spans`], which is available if the layer was configured
+/// [`with_open_span_tracking`](PerformanceEventsLayer::with_open_span_tracking). Since the layer is moved into the
+/// subscriber, this handle can be kept around to flush the spans later, for example in a `beforeunload` listener:
+///
+/// ```rust, ignore
+/// let perf_layer = tracing_web::performance_layer().with_open_span_tracking();
+/// let open_spans = perf_layer.open_spans().unwrap();
+/// // .. install the layer
+/// let on_unload = Closure::<dyn Fn()>::new(move || open_spans.flush());
+/// window.add_event_listener_with_callback("beforeunload", on_unload.as_ref().unchecked_ref())?;
+/// on_unload.forget();
+/// ```
+#[derive(Clone)]
+pub struct OpenSpans {
+    spans: Arc<Mutex<Vec<OpenSpan>>>,
+    backend: Arc<dyn PerfBackend>,
+}
+
+// A span that has been entered and not exited yet, with what is needed to measure it.
+struct OpenSpan {
+    id: span::Id,
+    name: &'static str,
+    entered: SpanStart,
+    measure_name: String,
+}
+
+enum SpanStart {
+    Mark(String),
+    Timestamp(f64),
+}
+
+impl OpenSpans {
+    /// Emit a `span-measure` from the last `span-enter` mark until now, for all spans that are currently entered.
+    ///
+    /// The spans are not measured again when flushed a second time, but are still measured as usual when they exit.
+    pub fn flush(&self) {
+        let open = match self.spans.lock() {
+            Ok(mut open) => std::mem::take(&mut *open),
+            Err(_) => return,
+        };
+        for span in open {
+            // Ignore errors
+            let _ = match span.entered {
+                SpanStart::Mark(mark) => {
+                    self.backend.measure(&span.measure_name, &mark, None, None)
+                }
+                SpanStart::Timestamp(start) => match self.backend.now() {
+                    Some(now) => self
+                        .backend
+                        .measure_between(&span.measure_name, start, now, None),
+                    None => Ok(()),
+                },
+            };
+        }
+    }
+    /// Log the spans that are currently entered to the console, as a group with one line per span.
+    ///
+    /// Each line shows the name of the span, its id as formatted by [`span_id_token`] and, with
+    /// [timestamp measures](PerformanceEventsLayer::with_timestamp_measures), the time since it was entered, e.g.
+    /// `fetch (span=42), entered 1234ms ago`. The spans are listed in the order they were entered, on all threads.
+    /// Spans that stay on the list long after they should have exited hint at leaked guards or futures that are never
+    /// polled to completion. Unlike [`flush`](Self::flush), this does not change the list.
+    pub fn dump(&self) {
+        let now = self.backend.now();
+        let lines: Vec<String> = match self.spans.lock() {
+            Ok(open) => open
+                .iter()
+                .map(|span| {
+                    let mut line = format!("{} ({})", span.name, span_id_token(&span.id));
+                    if let (SpanStart::Timestamp(start), Some(now)) = (&span.entered, now) {
+                        line.push_str(&format!(", entered {:.0}ms ago", now - start));
+                    }
+                    line
+                })
+                .collect(),
+            Err(_) => return,
+        };
+        let title = format!("open spans ({})", lines.len());
+        web_sys::console::group_1(&JsValue::from(title));
+        for line in lines {
+            web_sys::console::log_1(&JsValue::from(line));
+        }
+        web_sys::console::group_end();
+    }
+    fn push(&self, span: OpenSpan) {
+        if let Ok(mut open) = self.spans.lock() {
+            open.push(span);
+        }
+    }
+    fn remove(&self, id: &span::Id) {
+        if let Ok(mut open) = self.spans.lock() {
+            if let Some(idx) = open.iter().rposition(|span| &span.id == id) {
+                open.remove(idx);
+            }
+        }
+    }
+}
+
+// Collects the message and the remaining fields of an event, formatted as `field=value`.
+#[derive(Default)]
+struct EventVisitor {
+    message: Option<String>,
+    fields: String,
+}
+
+impl Visit for EventVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = Some(value.to_owned());
+        } else {
+            self.record_debug(field, &format_args!("{}", value));
+        }
+    }
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = Some(format!("{:?}", value));
+        } else {
+            if !self.fields.is_empty() {
+                self.fields.push(' ');
+            }
+            self.fields
+                .push_str(&format!("{}={:?}", field.name(), value));
+        }
+    }
+}
+
+// The color of the measures of a span in the dev-tools, captured from the field designated with `with_color_field`.
+struct DevtoolsColor(&'static str);
+
+// The trace context of a span, captured from the field designated with `with_propagation_field`.
+struct Propagation(String);
+
+thread_local! {
+    // The trace context of the currently entered spans on this thread, if they or their parents have one.
+    static ENTERED_PROPAGATION: RefCell<Vec<(span::Id, String)>> = RefCell::new(Vec::new());
+    // The number of sampled spans currently entered on this thread, to assign lanes with `with_lanes`.
+    static ACTIVE_SPANS: Cell<usize> = Cell::new(0);
+}
+
+// The nesting lane of a span, i.e. the number of other spans that were active on the thread when it was entered last.
+struct Lane(usize);
+
+// The spans a span follows from, with their name if they were still open when the link was recorded.
+struct FollowsFrom(Vec<(u64, Option<&'static str>)>);
+
+/// The trace context of the innermost entered span on the current thread, e.g. a `traceparent` header.
+///
+/// The value is taken from the field designated with [`PerformanceEventsLayer::with_propagation_field`] of the span
+/// or of its closest parent with the field, to be injected into the headers of outgoing requests:
+///
+/// ```rust, ignore
+/// if let Some(traceparent) = tracing_web::current_propagation_value() {
+///     headers.set("traceparent", &traceparent)?;
+/// }
+/// ```
+///
+/// Returns `None` if no span with a trace context is entered.
+pub fn current_propagation_value() -> Option<String> {
+    ENTERED_PROPAGATION.with(|entered| entered.borrow().last().map(|(_, value)| value.clone()))
+}
+
+// The category of a span, captured from the field designated with `with_category_field`.
+struct Category(String);
+
+// The current memory usage, as an object with the size of the wasm memory and, if available, the used JS heap.
+fn sample_memory() -> Object {
+    let memory = Object::new();
+    let byte_length_prop = JsString::from(wasm_bindgen::intern("byteLength"));
+    let wasm_prop = JsString::from(wasm_bindgen::intern("wasm"));
+    // With threads, the buffer is a SharedArrayBuffer, so look up the length dynamically
+    let buffer = wasm_bindgen::memory()
+        .unchecked_into::<js_sys::WebAssembly::Memory>()
+        .buffer();
+    if let Ok(wasm_size) = Reflect::get(&buffer, &byte_length_prop) {
+        Reflect::set(&memory, &wasm_prop, &wasm_size).unwrap();
+    }
+    let used_prop = JsString::from(wasm_bindgen::intern("usedJSHeapSize"));
+    let js_heap_prop = JsString::from(wasm_bindgen::intern("jsHeap"));
+    let js_heap = with_performance(|p| p.memory())
+        .filter(|perf_memory| perf_memory.is_object())
+        .and_then(|perf_memory| Reflect::get(&perf_memory, &used_prop).ok())
+        .filter(|used| !used.is_undefined());
+    if let Some(js_heap) = js_heap {
+        Reflect::set(&memory, &js_heap_prop, &js_heap).unwrap();
+    }
+    memory
+}
+
+// Map a color name to the palette allowed by the dev-tools extensibility API.
+fn devtools_color(name: &str) -> Option<&'static str> {
+    const PALETTE: [&str; 10] = [
+        "primary",
+        "primary-light",
+        "primary-dark",
+        "secondary",
+        "secondary-light",
+        "secondary-dark",
+        "tertiary",
+        "tertiary-light",
+        "tertiary-dark",
+        "error",
+    ];
+    let name = name.trim_matches('"');
+    if let Some(color) = PALETTE.iter().find(|&&color| color == name) {
+        return Some(color);
+    }
+    match name {
+        "blue" => Some("primary"),
+        "purple" => Some("secondary"),
+        "green" => Some("tertiary"),
+        "red" => Some("error"),
+        _ => None,
+    }
+}
+
+/// Format the id of a span as a token such as `span=42`, to find the output of the same span in different places.
+///
+/// This is the format of the span ids shown by [`MakeWebConsoleWriter::with_span_ids`],
+/// [`StructuredConsoleLayer::with_span_id`] and in the details of [`PerformanceEventsLayer::with_span_id_token`].
+///
+/// [`MakeWebConsoleWriter::with_span_ids`]: crate::MakeWebConsoleWriter::with_span_ids
+/// [`StructuredConsoleLayer::with_span_id`]: crate::StructuredConsoleLayer::with_span_id
+pub fn span_id_token(id: &span::Id) -> String {
+    format!("span={}", id.into_u64())
+}
+
+// Stored in the extensions of spans that have not been selected when sampling.
+struct SampledOut;
+
+/// A marker in the extensions of spans that are measured by a [`PerformanceEventsLayer`].
+///
+/// The marker is stored for every span the layer measures. If the layer samples spans, by a
+/// [ratio](PerformanceEventsLayer::with_sampling) or a [field](PerformanceEventsLayer::with_sample_field), only the
+/// spans that have been selected are marked. The marker is zero-sized, storing it does not allocate.
+///
+/// Other layers can look for the marker to decide whether to do additional work for a span, e.g. with
+/// `span.extensions().get::<PerfSampled>().is_some()`. Outside of layers, use [`is_perf_sampled`].
+#[derive(Debug, Clone, Copy)]
+pub struct PerfSampled;
+
+/// Check whether the span with the given `id` is measured by a [`PerformanceEventsLayer`] of the default subscriber.
+///
+/// This is useful for conditional instrumentation, e.g. to only collect expensive details for spans that show up in
+/// the timeline. Returns `false` if there is no such span or no performance layer is installed, and also if the
+/// default subscriber is not built on a [`Registry`](tracing_subscriber::Registry), where spans can not be looked
+/// up. If the layer does not sample spans, this returns `true` for all of them.
+///
+/// ```rust, ignore
+/// let span = tracing::info_span!("render");
+/// if span.id().is_some_and(|id| tracing_web::is_perf_sampled(&id)) {
+///     // ..
+/// }
+/// ```
+pub fn is_perf_sampled(id: &span::Id) -> bool {
+    tracing_core::dispatcher::get_default(|dispatch| {
+        dispatch
+            .downcast_ref::<Registry>()
+            .and_then(|registry| registry.span(id))
+            .is_some_and(|span| span.extensions().get::<PerfSampled>().is_some())
+    })
+}
+
+// A cheap, uniformly distributed point in [0, 1) to decide sampling with, seeded from the span id and the seed of
+// the layer.
+fn sample_point(id: &span::Id, seed: u64) -> f64 {
+    // splitmix64
+    let mut z = (id.into_u64() ^ seed.rotate_left(32)).wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
+
+// The time a span was last entered, stored in its extensions when the duration is shown in the measure name.
+struct EnteredAt(f64);
+
+// The time a span was first entered, stored in its extensions when measures are coalesced.
+struct FirstEnteredAt(f64);
+
+// Accumulated timings of a span, stored in its extensions when busy time is recorded.
+struct SpanTimings {
+    created: f64,
+    last: f64,
+    busy: f64,
+    idle: f64,
+}
+
 impl<S, N> PerformanceEventsLayer<S, N> {
     /// Change the way additional details are attached to performance events.
     ///
@@ -101,7 +791,10 @@ impl<S, N> PerformanceEventsLayer<S, N> {
     where
         N2: 'static + for<'writer> FormatFields<'writer>,
     {
-        self.with_details(FormatSpanFromFields { inner: fmt_fields })
+        self.with_details(FormatSpanFromFields {
+            inner: fmt_fields,
+            _reuse: PhantomData,
+        })
     }
     /// Change the way additional details are attached to performance events.
     ///
@@ -109,9 +802,402 @@ impl<S, N> PerformanceEventsLayer<S, N> {
     pub fn with_details<N2: FormatSpan>(self, fmt_details: N2) -> PerformanceEventsLayer<S, N2> {
         PerformanceEventsLayer {
             fmt_details,
+            options: self.options,
             _inner: PhantomData,
         }
     }
+    /// Stop attaching the fields of spans as details to performance events, undoing
+    /// [`with_details`](Self::with_details) and [`with_details_from_fields`](Self::with_details_from_fields).
+    ///
+    /// See [`performance_layer`] for the overhead of a layer without details. All other options are kept.
+    pub fn without_details(self) -> PerformanceEventsLayer<S, ()> {
+        self.with_details(())
+    }
+    /// Prefix the names of all performance events with a namespace.
+    ///
+    /// Events are then named `{namespace}:{span} [{id}]: {event}`, which makes it easy to tell them apart from
+    /// performance events of other libraries, e.g. in [`export_performance_trace`](crate::export_performance_trace).
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.options.namespace = Some(namespace.into());
+        self
+    }
+    /// Identify spans in the names of performance events by the value of a field, instead of their id.
+    ///
+    /// The value of the field is captured when the span is created, and replaces the numeric span id in the names,
+    /// e.g. `fetch [user-42]: span-enter` with a name key of `request_id`. Spans without the field are still
+    /// identified by their id.
+    pub fn with_name_key(mut self, field: &'static str) -> Self {
+        self.options.name_key = Some(field);
+        self
+    }
+    /// Augment the details attached to performance events with additional properties.
+    ///
+    /// The given function is called right before each mark or measure is emitted. When set, the attached details
+    /// become an object, holding the details formatted by [`FormatSpan`] (if any) in its `fields` property, to which
+    /// the function can add arbitrary further properties, e.g. a constant `app_version`.
+    pub fn with_detail_augment<F>(mut self, augment: F) -> Self
+    where
+        F: 'static + Fn(&mut Object, &SpanRef<'_, S>) + Send + Sync,
+    {
+        self.options.detail_augment = Some(Box::new(augment));
+        self
+    }
+    /// Record the cumulative busy and idle time of spans.
+    ///
+    /// Spans that are entered and exited multiple times, for example in a loop or when instrumenting a future, are
+    /// then additionally measured as a whole when they close. This `span-lifetime` measure starts at the creation of
+    /// the span, and its details are an object with the total time spent inside (`busy`) and outside (`idle`) the
+    /// span in milliseconds, which gives a truer picture than the individual `span-measure`s.
+    pub fn with_busy_time(mut self) -> Self {
+        self.options.busy_time = true;
+        self
+    }
+    /// Include the name and id of the parent span in the details of measures.
+    ///
+    /// The details of measures then become an object, with a `parent` property holding the `name` and `id` of the
+    /// parent span, if there is one. Together with the timing of the measures, this is enough for external tooling
+    /// to reconstruct the span tree, e.g. to build a flamegraph.
+    pub fn with_parent_info(mut self) -> Self {
+        self.options.parent_info = true;
+        self
+    }
+    /// Include a monotonically increasing sequence number in the details of marks.
+    ///
+    /// The details of marks then become an object, with the sequence number in its `seq` property. This orders marks
+    /// that share the same timestamp, which is common given the limited resolution of timers in browsers.
+    pub fn with_sequence_numbers(mut self) -> Self {
+        self.options.sequence = Some(Arc::new(AtomicU64::new(0)));
+        self
+    }
+    /// Only emit performance events for a fraction of spans.
+    ///
+    /// Whether a span is sampled is decided once, when it is created, so that all marks and measures of a sampled
+    /// span are emitted consistently. A `ratio` of `1.0` samples every span, `0.0` none. This keeps the overhead of
+    /// spans in hot code paths proportional to the sampling rate.
+    ///
+    /// The decision is deterministic, derived from the span id and the [seed](Self::with_sampling_seed) of the layer.
+    /// Note that the registry reuses the ids of closed spans, so a span that is repeatedly created in a loop may get
+    /// the same id, and with it the same decision, each time. Change the seed to sample a different set of ids.
+    pub fn with_sampling(mut self, ratio: f64) -> Self {
+        self.options.sampling = Some(ratio.clamp(0.0, 1.0));
+        self
+    }
+    /// Set the seed that the [sampling](Self::with_sampling) decisions are derived from, `0` by default.
+    pub fn with_sampling_seed(mut self, seed: u64) -> Self {
+        self.options.sample_seed = seed;
+        self
+    }
+    /// Decide whether to sample a span from a boolean field, e.g. `perf_sample = true`, overriding the ratio.
+    ///
+    /// Spans with the field set to `true` are always sampled and spans with the field set to `false` never are,
+    /// regardless of the ratio given to [`with_sampling`](Self::with_sampling). All other spans are sampled by the
+    /// ratio, if any. Combine this with a low ratio to trace a few spans of interest under load. The field is read once,
+    /// when the span is created, so it has to be set in the span macro rather than recorded later.
+    pub fn with_sample_field(mut self, field: &'static str) -> Self {
+        self.options.sample_field = Some(field);
+        self
+    }
+    /// Additionally emit a `span-create` mark when a span is created.
+    ///
+    /// By default, a span only shows up in the timeline when it is first entered. Spans can be created long before
+    /// that, e.g. for instrumented futures that are polled only later, in which case the time of creation is useful
+    /// to see as well.
+    pub fn with_create_marks(mut self, enabled: bool) -> Self {
+        self.options.create_marks = enabled;
+        self
+    }
+    /// Append the duration to the name of each `span-measure`, e.g. `render [12]: span-measure (8.3ms)`.
+    ///
+    /// This makes it possible to tell the duration from the name alone, when looking through a list of entries.
+    /// Note that the measure names then differ each time a span is entered.
+    pub fn with_duration_in_name(mut self) -> Self {
+        self.options.duration_in_name = true;
+        self
+    }
+    /// Annotate the timeline of the performance panel with a [`console.timeStamp`] for each event.
+    ///
+    /// The time stamps are labelled with the message of the event, prefixed with the [namespace] if one is set. They
+    /// are lighter than marks, and only show up in the dev-tools while recording a profile.
+    ///
+    /// [`console.timeStamp`]: https://developer.mozilla.org/en-US/docs/Web/API/console/timeStamp_static
+    /// [namespace]: Self::with_namespace
+    pub fn with_event_timestamps(mut self) -> Self {
+        self.options.event_timestamps = true;
+        self
+    }
+    /// Emit a mark for each event, named `event: {message}` and prefixed with the [namespace] if one is set.
+    ///
+    /// The details of the mark are the other fields of the event, formatted as `field=value`. See
+    /// [`with_inherited_fields`](Self::with_inherited_fields) to include the fields of the enclosing spans as well.
+    ///
+    /// [namespace]: Self::with_namespace
+    pub fn with_event_marks(mut self) -> Self {
+        self.options.event_marks = true;
+        self
+    }
+    /// Emit a mark for each event, named after the enclosing span, to tie it to the entries of that span.
+    ///
+    /// Like [`with_event_marks`](Self::with_event_marks), but the marks of events within a span are named like the
+    /// other marks of the span, e.g. `fetch [3]: event: retrying`, so that they are found next to them in the
+    /// timeline. Marks of events outside of any span are named `event: {message}` as usual.
+    pub fn with_span_event_marks(mut self) -> Self {
+        self.options.event_marks = true;
+        self.options.event_span_names = true;
+        self
+    }
+    /// Include the details of up to `depth` enclosing spans in the details of event marks.
+    ///
+    /// The details of the spans, as formatted by the [`FormatSpan`] of this layer, are prepended to the fields of the
+    /// event, starting with the outermost span. A `depth` of `0`, the default, only includes the fields of the event.
+    pub fn with_inherited_fields(mut self, depth: usize) -> Self {
+        self.options.inherit_depth = depth;
+        self
+    }
+    /// Measure spans with explicit timestamps, instead of between a `span-enter` and a `span-exit` mark.
+    ///
+    /// This uses the options object form of `performance.measure`, with the numeric `start` and `end` taken from
+    /// `performance.now()` and the `detail` in a single call. No separate marks are emitted for entering and exiting
+    /// a span, which halves the number of entries and does not depend on the marks being present in the timeline,
+    /// e.g. after they have been cleared.
+    pub fn with_timestamp_measures(mut self) -> Self {
+        self.options.timestamp_measures = true;
+        self
+    }
+    /// Choose whether a `span-record` mark is emitted each time a span records values.
+    ///
+    /// Marks are emitted by default. Without them, the recorded values are still added to the details of the span,
+    /// so that following marks and measures, most importantly the final `span-measure`, carry the latest values.
+    /// Since spans recording values in a loop quickly flood the timeline, `with_record_marks(false)` is the
+    /// recommended setting for production.
+    pub fn with_record_marks(mut self, enabled: bool) -> Self {
+        self.options.record_marks = enabled;
+        self
+    }
+    /// Choose whether `span-enter` and `span-exit` marks are emitted, in addition to the `span-measure` of each time a
+    /// span is entered.
+    ///
+    /// Marks are emitted by default. Without them, spans are measured with explicit timestamps, as with
+    /// [`with_timestamp_measures`](Self::with_timestamp_measures), so `with_marks(false)` only leaves the measures in
+    /// the timeline. This does not change the other option, and `with_marks(true)` does not turn off timestamp
+    /// measures, which never emit these marks.
+    pub fn with_marks(mut self, enabled: bool) -> Self {
+        self.options.emit_marks = enabled;
+        self
+    }
+    /// Color the measures of spans in the performance panel by the value of a field, e.g. `perf_color = "primary"`.
+    ///
+    /// The value of the field is captured when the span is created, and added to the details of its measures as
+    /// `devtools.color`, as understood by the [extensibility API] of the Chrome dev-tools. Allowed values are the
+    /// colors of the dev-tools palette, i.e. `primary`, `secondary` and `tertiary`, each optionally suffixed with
+    /// `-light` or `-dark`, and `error`. The names `blue`, `purple`, `green` and `red` are mapped to the palette as
+    /// well, other values are ignored. The measures are shown in a custom track, named after the [namespace] or
+    /// `tracing` if no namespace is set.
+    ///
+    /// [extensibility API]: https://developer.chrome.com/docs/devtools/performance/extension
+    /// [namespace]: Self::with_namespace
+    pub fn with_color_field(mut self, field: &'static str) -> Self {
+        self.options.color_field = Some(field);
+        self
+    }
+    /// Tag the measures of spans with a category from the value of a field, e.g. `phase = "network"`.
+    ///
+    /// The value of the field is captured when the span is created, and added to the details of its measures as
+    /// `category`. This makes it easy to filter the measures of a kind of operation in analysis tools, e.g. in the
+    /// console with
+    ///
+    /// ```js
+    /// performance.getEntriesByType("measure").filter(m => m.detail?.category === "network")
+    /// ```
+    pub fn with_category_field(mut self, field: &'static str) -> Self {
+        self.options.category_field = Some(field);
+        self
+    }
+    /// Attach a build id to the details of all marks and measures of spans, as `build`.
+    ///
+    /// This makes it possible to correlate performance data across deploys. The id is typically sourced at compile
+    /// time, e.g. from an environment variable set by the build pipeline:
+    ///
+    /// ```rust, ignore
+    /// let perf_layer = tracing_web::performance_layer().with_build_id(env!("BUILD_ID"));
+    /// ```
+    ///
+    /// With a build id, details are always attached as an object, with the formatted fields in `fields`.
+    pub fn with_build_id(mut self, build_id: &str) -> Self {
+        self.options.build_id = Some(build_id.to_owned());
+        self
+    }
+    /// Capture the trace context of spans from the value of a field, e.g. `traceparent`, for distributed tracing.
+    ///
+    /// The value is captured when the span is created or records the field, and added to the details of its marks
+    /// and measures under the name of the field. While the span, or one of its children, is entered, the value is
+    /// available from [`current_propagation_value`], e.g. to be injected into the headers of `fetch` requests. To
+    /// show it in the console as well, pass that function to [`MakeWebConsoleWriter::with_correlation`].
+    ///
+    /// The trace context is captured regardless of [sampling](Self::with_sampling).
+    ///
+    /// [`MakeWebConsoleWriter::with_correlation`]: crate::MakeWebConsoleWriter::with_correlation
+    pub fn with_propagation_field(mut self, field: &'static str) -> Self {
+        self.options.propagation_field = Some(field);
+        self
+    }
+    /// Choose whether to sample the memory usage when a span exits, and add it to the details of its measure.
+    ///
+    /// The details then contain `memory.wasm`, the size of the memory of the WebAssembly instance in bytes, and
+    /// `memory.jsHeap`, the used size of the JS heap from `performance.memory`. The latter is non-standard and only
+    /// available in Chromium based browsers, and omitted elsewhere.
+    pub fn with_memory_sampling(mut self, enabled: bool) -> Self {
+        self.options.memory_sampling = enabled;
+        self
+    }
+    /// Choose whether to add the nesting lane of a span to the details of its measure, as `lane`.
+    ///
+    /// The lane is the number of spans that were entered on the same thread, and not yet exited, when the span was
+    /// entered. Unlike the depth of a span in its trace, this reflects spans of unrelated futures that are interleaved
+    /// by the executor, so that tooling can lay out overlapping measures in separate lanes. Only sampled spans are
+    /// counted, see [`with_sampling`](Self::with_sampling).
+    pub fn with_lanes(mut self, enabled: bool) -> Self {
+        self.options.lanes = enabled;
+        self
+    }
+    /// Choose whether to add the spans a span [follows from](tracing::Span::follows_from) to the details of its
+    /// measure, as `follows`.
+    ///
+    /// Each link is an object with the `id` of the span it points to, and its `name` if that span was not closed yet
+    /// when the link was recorded. This shows causal relationships between spans that are otherwise unrelated in the
+    /// timeline, e.g. a task and the request that spawned it.
+    pub fn with_follows_from(mut self, enabled: bool) -> Self {
+        self.options.follows_from = enabled;
+        self
+    }
+    /// Choose whether to show the measures of nested spans as a tree in the performance panel of the dev-tools.
+    ///
+    /// The dev-tools nest measures whose time ranges are contained in each other, but only within the same track. With
+    /// this option, each span is put in the custom track of [`with_color_field`](Self::with_color_field), in the color
+    /// of its closest ancestor with a color, or `primary` if there is none. The name and id of the parent are added to
+    /// the details as with [`with_parent_info`](Self::with_parent_info), to follow the hierarchy in other tools.
+    pub fn with_nested_tracks(mut self, enabled: bool) -> Self {
+        self.options.nested_tracks = enabled;
+        self
+    }
+    /// Choose whether to add the id of the span to the details of its marks and measures, as `span`.
+    ///
+    /// The id is formatted by [`span_id_token`], e.g. `span=42`, which is the same token shown in front of console
+    /// messages by [`MakeWebConsoleWriter::with_span_ids`]. To find the timeline entries of a span logged in the
+    /// console, search for the token in the details of the entries, e.g. by evaluating
+    /// `performance.getEntries().filter(e => e.detail?.span === "span=42")` in the console.
+    ///
+    /// [`MakeWebConsoleWriter::with_span_ids`]: crate::MakeWebConsoleWriter::with_span_ids
+    pub fn with_span_id_token(mut self, enabled: bool) -> Self {
+        self.options.span_id_token = enabled;
+        self
+    }
+    /// Change the names of the properties of structured details, to match the schema expected by analysis tools.
+    ///
+    /// This applies to the properties added by this layer within the `detail` of marks and measures. The options
+    /// passed to `performance.mark` and `performance.measure` themselves, i.e. `detail`, `start` and `end`, are
+    /// defined by the User Timing spec and can not be changed, nor can the `devtools` property of
+    /// [`with_color_field`](Self::with_color_field). To reshape the details further, see
+    /// [`with_detail_augment`](Self::with_detail_augment).
+    pub fn with_detail_keys(mut self, keys: DetailKeys) -> Self {
+        self.options.detail_keys = keys;
+        self
+    }
+    /// Coalesce all the times a span is entered into a single `span-measure`, from the first enter to its close.
+    ///
+    /// Spans of futures are entered and exited each time the future is polled, which produces many tiny measures.
+    /// With this option, no marks are emitted when entering and exiting spans, and a single measure is emitted when
+    /// the span closes instead. See also [`with_busy_time`](Self::with_busy_time) for the time actually spent in the
+    /// span.
+    pub fn with_coalesced_measures(mut self) -> Self {
+        self.options.coalesce = true;
+        self
+    }
+    /// Pass each `span-measure` to `callback` as well, e.g. to forward it to a real user monitoring (RUM) SDK.
+    ///
+    /// The callback is called with the name, the start time and the duration of the measure in milliseconds, as
+    /// obtained from `performance.now()`, and the details formatted by the [`FormatSpan`] of this layer, if any.
+    pub fn with_measure_callback<F>(mut self, callback: F) -> Self
+    where
+        F: 'static + Fn(&str, f64, f64, Option<&str>) + Send + Sync,
+    {
+        self.options.measure_callback = Some(Box::new(callback));
+        self
+    }
+    /// Pass each `span-measure` to `sink` as a [`Measure`], e.g. to aggregate timings in Rust.
+    ///
+    /// Unlike polling `performance.getEntries()`, this does not depend on the performance buffer of the browser, which
+    /// is limited in size and can be cleared by other scripts. Measures that complete while the sink is running, e.g.
+    /// because it enters a span itself, are not passed to it.
+    pub fn with_measure_sink<F>(mut self, sink: F) -> Self
+    where
+        F: 'static + FnMut(Measure) + Send,
+    {
+        self.options.measure_sink = Some(Mutex::new(Box::new(sink)));
+        self
+    }
+    /// Keep track of the spans that are currently entered, to be able to [flush](OpenSpans::flush) them.
+    ///
+    /// With [coalesced measures](Self::with_coalesced_measures), spans are tracked from their first enter until they
+    /// close, matching the measure emitted for them.
+    ///
+    /// See [`OpenSpans`] for an example.
+    pub fn with_open_span_tracking(mut self) -> Self {
+        self.options.open_spans = Some(OpenSpans {
+            spans: Arc::default(),
+            backend: self.options.backend.clone(),
+        });
+        self
+    }
+    /// Emit the performance events to `backend`, instead of the [`performance`] object of the current global scope.
+    ///
+    /// This is mainly useful to test instrumentation with a mock backend, see [`PerfBackend`]. Call this before
+    /// getting a handle with [`open_spans`](Self::open_spans).
+    ///
+    /// [`performance`]: https://developer.mozilla.org/en-US/docs/Web/API/Performance
+    pub fn with_backend(mut self, backend: impl PerfBackend) -> Self {
+        self.options.backend = Arc::new(backend);
+        if let Some(open_spans) = &mut self.options.open_spans {
+            open_spans.backend = self.options.backend.clone();
+        }
+        self
+    }
+    /// Get a handle to the spans that are currently entered, if enabled with
+    /// [`with_open_span_tracking`](Self::with_open_span_tracking).
+    pub fn open_spans(&self) -> Option<OpenSpans> {
+        self.options.open_spans.clone()
+    }
+    /// Emit a final measure for all spans that are currently entered, see [`OpenSpans::flush`].
+    ///
+    /// Does nothing, unless enabled with [`with_open_span_tracking`](Self::with_open_span_tracking).
+    pub fn flush_open_spans(&self) {
+        if let Some(open_spans) = &self.options.open_spans {
+            open_spans.flush();
+        }
+    }
+    /// Log the spans that are currently entered to the console, see [`OpenSpans::dump`].
+    ///
+    /// Does nothing, unless enabled with [`with_open_span_tracking`](Self::with_open_span_tracking).
+    pub fn dump_open_spans(&self) {
+        if let Some(open_spans) = &self.options.open_spans {
+            open_spans.dump();
+        }
+    }
+    /// Clear the marks and measures emitted by this layer from the performance timeline.
+    ///
+    /// Call this e.g. from the navigation hook of a router, to measure each page of a single page application on its
+    /// own. Only entries in the [namespace] of this layer are cleared. If no namespace is set, the entries of this
+    /// layer can not be told apart from those of other libraries, so nothing is cleared and `false` is returned.
+    ///
+    /// [namespace]: Self::with_namespace
+    pub fn clear(&self) -> bool {
+        match &self.options.namespace {
+            Some(namespace) => {
+                self.options.backend.clear(&format!("{namespace}:"));
+                true
+            }
+            None => false,
+        }
+    }
 }
 
 impl<S, N> PerformanceEventsLayer<S, N>
@@ -119,22 +1205,250 @@ where
     S: Subscriber + for<'lookup> LookupSpan<'lookup>,
     N: FormatSpan,
 {
-    fn template_name(span: &SpanRef<'_, S>, event_name: &str) -> String {
-        let span_id = span.id().into_u64();
+    fn template_name(&self, span: &SpanRef<'_, S>, event_name: &str) -> String {
+        let span_id = match span.extensions().get::<NameKey>() {
+            Some(NameKey(key)) => key.clone(),
+            None => span.id().into_u64().to_string(),
+        };
         let name = span.metadata().name();
-        format!("{name} [{span_id}]: {event_name}")
+        match &self.options.namespace {
+            Some(namespace) => format!("{namespace}:{name} [{span_id}]: {event_name}"),
+            None => format!("{name} [{span_id}]: {event_name}"),
+        }
+    }
+    fn span_create_name(&self, span: &SpanRef<'_, S>) -> String {
+        self.template_name(span, "span-create")
     }
     fn span_enter_name(&self, span: &SpanRef<'_, S>) -> String {
-        Self::template_name(span, "span-enter")
+        self.template_name(span, "span-enter")
     }
     fn span_exit_name(&self, span: &SpanRef<'_, S>) -> String {
-        Self::template_name(span, "span-exit")
+        self.template_name(span, "span-exit")
     }
     fn span_record_name(&self, span: &SpanRef<'_, S>) -> String {
-        Self::template_name(span, "span-record")
+        self.template_name(span, "span-record")
+    }
+    fn span_measure_name(&self, span: &SpanRef<'_, S>, duration: Option<f64>) -> String {
+        match duration {
+            Some(duration) => self.template_name(span, &format!("span-measure ({duration:.1}ms)")),
+            None => self.template_name(span, "span-measure"),
+        }
+    }
+    fn span_lifetime_name(&self, span: &SpanRef<'_, S>) -> String {
+        self.template_name(span, "span-lifetime")
+    }
+    // Whether spans are measured with explicit timestamps, instead of between their enter and exit marks.
+    fn measures_by_timestamp(&self) -> bool {
+        self.options.timestamp_measures || !self.options.emit_marks
+    }
+    // Whether spans are sampled at all, either by a ratio or by a field.
+    fn samples(&self) -> bool {
+        self.options.sampling.is_some() || self.options.sample_field.is_some()
+    }
+    fn is_sampled(&self, span: &SpanRef<'_, S>) -> bool {
+        !self.samples() || span.extensions().get::<SampledOut>().is_none()
+    }
+    // Decide whether a new span is sampled, by its sampling field if set, or else by the sampling ratio.
+    fn sample_span(&self, span: &SpanRef<'_, S>, attrs: &span::Attributes<'_>) -> bool {
+        if let Some(field) = self.options.sample_field {
+            let mut visitor = FieldValueVisitor { field, value: None };
+            attrs.record(&mut visitor);
+            match visitor.value.as_deref() {
+                Some("true") => return true,
+                Some("false") => return false,
+                _ => {}
+            }
+        }
+        match self.options.sampling {
+            Some(ratio) => sample_point(&span.id(), self.options.sample_seed) < ratio,
+            None => true,
+        }
+    }
+    fn flat_details(&self, span: &SpanRef<'_, S>) -> Option<JsValue> {
+        self.fmt_details
+            .find_details(&span.extensions())
+            .map(JsValue::from)
+    }
+    fn make_mark_details(&self, span: &SpanRef<'_, S>) -> Option<JsValue> {
+        if self.options.detail_augment.is_none()
+            && self.options.sequence.is_none()
+            && self.options.build_id.is_none()
+            && self.options.propagation_field.is_none()
+            && !self.options.span_id_token
+        {
+            return self.flat_details(span);
+        }
+        let details = self.structured_details(span);
+        if let Some(sequence) = &self.options.sequence {
+            let seq = sequence.fetch_add(1, Ordering::Relaxed);
+            let seq_prop = JsString::from(wasm_bindgen::intern(self.options.detail_keys.seq));
+            Reflect::set(&details, &seq_prop, &JsValue::from(seq as f64)).unwrap();
+        }
+        Some(self.finish_details(details, span))
+    }
+    // Details as an object, with the formatted details, if any, in the `fields` property.
+    fn structured_details(&self, span: &SpanRef<'_, S>) -> Object {
+        let details = Object::new();
+        if let Some(fields) = self.fmt_details.find_details(&span.extensions()) {
+            let fields_prop = JsString::from(wasm_bindgen::intern(self.options.detail_keys.fields));
+            Reflect::set(&details, &fields_prop, &JsValue::from(fields)).unwrap();
+        }
+        if let Some(build_id) = &self.options.build_id {
+            let build_prop = JsString::from(wasm_bindgen::intern(self.options.detail_keys.build));
+            Reflect::set(&details, &build_prop, &JsValue::from(build_id.as_str())).unwrap();
+        }
+        if self.options.span_id_token {
+            let span_prop = JsString::from(wasm_bindgen::intern(self.options.detail_keys.span));
+            Reflect::set(
+                &details,
+                &span_prop,
+                &JsValue::from(span_id_token(&span.id())),
+            )
+            .unwrap();
+        }
+        if let (Some(field), Some(Propagation(value))) = (
+            self.options.propagation_field,
+            span.extensions().get::<Propagation>(),
+        ) {
+            let field_prop = JsString::from(wasm_bindgen::intern(field));
+            Reflect::set(&details, &field_prop, &JsValue::from(value.as_str())).unwrap();
+        }
+        details
+    }
+    fn capture_propagation(&self, span: &SpanRef<'_, S>, values: &dyn RecordFields) {
+        if let Some(field) = self.options.propagation_field {
+            let mut visitor = FieldValueVisitor { field, value: None };
+            values.record(&mut visitor);
+            if let Some(value) = visitor.value {
+                span.extensions_mut().replace(Propagation(value));
+            }
+        }
+    }
+    fn make_measure_details(&self, span: &SpanRef<'_, S>) -> Option<JsValue> {
+        if self.options.nested_tracks {
+            self.inherit_track(span);
+        }
+        if self.options.detail_augment.is_none()
+            && !self.options.parent_info
+            && self.options.color_field.is_none()
+            && self.options.category_field.is_none()
+            && self.options.build_id.is_none()
+            && self.options.propagation_field.is_none()
+            && !self.options.memory_sampling
+            && !self.options.lanes
+            && !self.options.follows_from
+            && !self.options.span_id_token
+            && !self.options.nested_tracks
+        {
+            return self.flat_details(span);
+        }
+        Some(self.finish_details(self.structured_measure_details(span), span))
+    }
+    // Details of measures, which carry some additional information compared to marks.
+    fn structured_measure_details(&self, span: &SpanRef<'_, S>) -> Object {
+        let details = self.structured_details(span);
+        if self.options.parent_info || self.options.nested_tracks {
+            if let Some(parent) = span.parent() {
+                let parent_info = Object::new();
+                let name_prop = JsString::from(wasm_bindgen::intern("name"));
+                let id_prop = JsString::from(wasm_bindgen::intern("id"));
+                let parent_prop =
+                    JsString::from(wasm_bindgen::intern(self.options.detail_keys.parent));
+                let parent_name = JsValue::from(parent.metadata().name());
+                let parent_id = JsValue::from(parent.id().into_u64() as f64);
+                Reflect::set(&parent_info, &name_prop, &parent_name).unwrap();
+                Reflect::set(&parent_info, &id_prop, &parent_id).unwrap();
+                Reflect::set(&details, &parent_prop, &parent_info).unwrap();
+            }
+        }
+        if self.options.memory_sampling {
+            let memory_prop = JsString::from(wasm_bindgen::intern(self.options.detail_keys.memory));
+            Reflect::set(&details, &memory_prop, &sample_memory()).unwrap();
+        }
+        if let Some(Lane(lane)) = span.extensions().get::<Lane>() {
+            let lane_prop = JsString::from(wasm_bindgen::intern(self.options.detail_keys.lane));
+            Reflect::set(&details, &lane_prop, &JsValue::from(*lane as f64)).unwrap();
+        }
+        if let Some(FollowsFrom(links)) = span.extensions().get::<FollowsFrom>() {
+            let id_prop = JsString::from(wasm_bindgen::intern("id"));
+            let name_prop = JsString::from(wasm_bindgen::intern("name"));
+            let follows_prop =
+                JsString::from(wasm_bindgen::intern(self.options.detail_keys.follows));
+            let follows = Array::new();
+            for &(id, name) in links {
+                let link = Object::new();
+                Reflect::set(&link, &id_prop, &JsValue::from(id as f64)).unwrap();
+                if let Some(name) = name {
+                    Reflect::set(&link, &name_prop, &JsValue::from(name)).unwrap();
+                }
+                follows.push(&link);
+            }
+            Reflect::set(&details, &follows_prop, &follows).unwrap();
+        }
+        if let Some(Category(category)) = span.extensions().get::<Category>() {
+            let category_prop =
+                JsString::from(wasm_bindgen::intern(self.options.detail_keys.category));
+            Reflect::set(&details, &category_prop, &JsValue::from(category.as_str())).unwrap();
+        }
+        if let Some(DevtoolsColor(color)) = span.extensions().get::<DevtoolsColor>() {
+            let devtools = Object::new();
+            let track = self.options.namespace.as_deref().unwrap_or("tracing");
+            let data_type_prop = JsString::from(wasm_bindgen::intern("dataType"));
+            let track_prop = JsString::from(wasm_bindgen::intern("track"));
+            let color_prop = JsString::from(wasm_bindgen::intern("color"));
+            let devtools_prop = JsString::from(wasm_bindgen::intern("devtools"));
+            let data_type = JsValue::from(wasm_bindgen::intern("track-entry"));
+            Reflect::set(&devtools, &data_type_prop, &data_type).unwrap();
+            Reflect::set(&devtools, &track_prop, &JsValue::from(track)).unwrap();
+            Reflect::set(&devtools, &color_prop, &JsValue::from(*color)).unwrap();
+            Reflect::set(&details, &devtools_prop, &devtools).unwrap();
+        }
+        details
+    }
+    // Give a span the color of its closest ancestor with a color, so that its measures share the track of the parent.
+    fn inherit_track(&self, span: &SpanRef<'_, S>) {
+        if span.extensions().get::<DevtoolsColor>().is_some() {
+            return;
+        }
+        let color = span
+            .scope()
+            .skip(1)
+            .find_map(|ancestor| {
+                ancestor
+                    .extensions()
+                    .get::<DevtoolsColor>()
+                    .map(|&DevtoolsColor(color)| color)
+            })
+            .unwrap_or("primary");
+        span.extensions_mut().insert(DevtoolsColor(color));
+    }
+    fn report_measure(&self, span: &SpanRef<'_, S>, name: &str, start: f64, end: f64) {
+        if self.options.measure_callback.is_none() && self.options.measure_sink.is_none() {
+            return;
+        }
+        // Release the extensions before calling out, in case the callback records to the span
+        let details = self
+            .fmt_details
+            .find_details(&span.extensions())
+            .map(str::to_owned);
+        if let Some(callback) = &self.options.measure_callback {
+            callback(name, start, end - start, details.as_deref());
+        }
+        // A sink that is already locked is reporting a measure of its own, which is skipped instead of deadlocking
+        if let Some(Ok(mut sink)) = self.options.measure_sink.as_ref().map(Mutex::try_lock) {
+            sink(Measure {
+                name: name.to_owned(),
+                start,
+                duration: end - start,
+                detail: details,
+            });
+        }
     }
-    fn span_measure_name(&self, span: &SpanRef<'_, S>) -> String {
-        Self::template_name(span, "span-measure")
+    fn finish_details(&self, mut details: Object, span: &SpanRef<'_, S>) -> JsValue {
+        if let Some(augment) = &self.options.detail_augment {
+            augment(&mut details, span);
+        }
+        details.into()
     }
 }
 
@@ -145,55 +1459,330 @@ where
 {
     fn on_new_span(&self, attrs: &span::Attributes<'_>, span: &span::Id, ctx: Context<'_, S>) {
         let span = ctx.span(span).expect("can't find span, this is a bug");
+        self.capture_propagation(&span, attrs);
 
+        if self.samples() && !self.sample_span(&span, attrs) {
+            span.extensions_mut().insert(SampledOut);
+            return;
+        }
+        span.extensions_mut().replace(PerfSampled);
         self.fmt_details
             .add_details(&mut span.extensions_mut(), attrs);
+        if let Some(name_key) = self.options.name_key {
+            let mut visitor = FieldValueVisitor {
+                field: name_key,
+                value: None,
+            };
+            attrs.record(&mut visitor);
+            if let Some(key) = visitor.value {
+                span.extensions_mut().insert(NameKey(key));
+            }
+        }
+        if let Some(color_field) = self.options.color_field {
+            let mut visitor = FieldValueVisitor {
+                field: color_field,
+                value: None,
+            };
+            attrs.record(&mut visitor);
+            if let Some(color) = visitor.value.as_deref().and_then(devtools_color) {
+                span.extensions_mut().insert(DevtoolsColor(color));
+            }
+        }
+        if let Some(category_field) = self.options.category_field {
+            let mut visitor = FieldValueVisitor {
+                field: category_field,
+                value: None,
+            };
+            attrs.record(&mut visitor);
+            if let Some(category) = visitor.value {
+                span.extensions_mut().insert(Category(category));
+            }
+        }
+        if self.options.busy_time {
+            if let Some(now) = self.options.backend.now() {
+                span.extensions_mut().insert(SpanTimings {
+                    created: now,
+                    last: now,
+                    busy: 0.0,
+                    idle: 0.0,
+                });
+            }
+        }
+        if self.options.create_marks {
+            let mark_name = self.span_create_name(&span);
+            let details = self.make_mark_details(&span);
+            // Ignore errors
+            let _ = self.options.backend.mark(&mark_name, details.as_ref());
+        }
     }
     fn on_record(&self, span: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
         let span = ctx.span(span).expect("can't find span, this is a bug");
+        self.capture_propagation(&span, values);
+        if !self.is_sampled(&span) {
+            return;
+        }
         self.fmt_details
             .record_values(&mut span.extensions_mut(), values);
+        if !self.options.record_marks {
+            return;
+        }
 
         let mark_name = self.span_record_name(&span);
-        let _ = PERF.with(|p| {
-            if let Some(details) = self.fmt_details.find_details(&span.extensions()) {
-                p.mark_detailed(&mark_name, details)
-            } else {
-                p.mark(&mark_name)
-            }
-        }); // Ignore errors
+        let details = self.make_mark_details(&span);
+        // Ignore errors
+        let _ = self.options.backend.mark(&mark_name, details.as_ref());
     }
     fn on_enter(&self, span: &span::Id, ctx: Context<'_, S>) {
         let span = ctx.span(span).expect("can't find span, this is a bug");
-        let mark_name = self.span_enter_name(&span);
-        let _ = PERF.with(|p| {
-            if let Some(details) = self.fmt_details.find_details(&span.extensions()) {
-                p.mark_detailed(&mark_name, details)
-            } else {
-                p.mark(&mark_name)
+        if self.options.propagation_field.is_some() {
+            let value = span.scope().find_map(|span| {
+                let ext = span.extensions();
+                ext.get::<Propagation>()
+                    .map(|Propagation(value)| value.clone())
+            });
+            if let Some(value) = value {
+                ENTERED_PROPAGATION.with(|entered| entered.borrow_mut().push((span.id(), value)));
+            }
+        }
+        if !self.is_sampled(&span) {
+            return;
+        }
+        if self.options.lanes {
+            let lane = ACTIVE_SPANS.with(|active| active.replace(active.get() + 1));
+            span.extensions_mut().replace(Lane(lane));
+        }
+        if let Some(timings) = span.extensions_mut().get_mut::<SpanTimings>() {
+            let now = self.options.backend.now().unwrap_or(timings.last);
+            timings.idle += now - timings.last;
+            timings.last = now;
+        }
+        if self.options.coalesce {
+            if span.extensions().get::<FirstEnteredAt>().is_none() {
+                if let Some(now) = self.options.backend.now() {
+                    span.extensions_mut().insert(FirstEnteredAt(now));
+                    // Coalesced spans stay open until they close, rather than until they exit
+                    if let Some(open_spans) = &self.options.open_spans {
+                        open_spans.push(OpenSpan {
+                            id: span.id(),
+                            name: span.metadata().name(),
+                            measure_name: self.span_measure_name(&span, None),
+                            entered: SpanStart::Timestamp(now),
+                        });
+                    }
+                }
+            }
+            return;
+        }
+        let entered = self.options.backend.now();
+        if self.options.duration_in_name
+            || self.measures_by_timestamp()
+            || self.options.measure_callback.is_some()
+            || self.options.measure_sink.is_some()
+        {
+            if let Some(now) = entered {
+                span.extensions_mut().replace(EnteredAt(now));
+            }
+        }
+        if self.measures_by_timestamp() {
+            if let (Some(open_spans), Some(now)) = (&self.options.open_spans, entered) {
+                open_spans.push(OpenSpan {
+                    id: span.id(),
+                    name: span.metadata().name(),
+                    measure_name: self.span_measure_name(&span, None),
+                    entered: SpanStart::Timestamp(now),
+                });
             }
-        }); // Ignore errors
+            return;
+        }
+        let mark_name = self.span_enter_name(&span);
+        let details = self.make_mark_details(&span);
+        // Ignore errors
+        let _ = self.options.backend.mark(&mark_name, details.as_ref());
+        if let Some(open_spans) = &self.options.open_spans {
+            open_spans.push(OpenSpan {
+                id: span.id(),
+                name: span.metadata().name(),
+                measure_name: self.span_measure_name(&span, None),
+                entered: SpanStart::Mark(mark_name),
+            });
+        }
     }
     fn on_exit(&self, span: &span::Id, ctx: Context<'_, S>) {
         let span = ctx.span(span).expect("can't find span, this is a bug");
+        if self.options.propagation_field.is_some() {
+            ENTERED_PROPAGATION.with(|entered| {
+                let mut entered = entered.borrow_mut();
+                if let Some(pos) = entered.iter().rposition(|(id, _)| *id == span.id()) {
+                    entered.remove(pos);
+                }
+            });
+        }
+        if !self.is_sampled(&span) {
+            return;
+        }
+        if self.options.lanes {
+            ACTIVE_SPANS.with(|active| active.set(active.get().saturating_sub(1)));
+        }
+        // Coalesced spans are removed when they close instead
+        if let Some(open_spans) = self
+            .options
+            .open_spans
+            .as_ref()
+            .filter(|_| !self.options.coalesce)
+        {
+            open_spans.remove(&span.id());
+        }
+        if let Some(timings) = span.extensions_mut().get_mut::<SpanTimings>() {
+            let now = self.options.backend.now().unwrap_or(timings.last);
+            timings.busy += now - timings.last;
+            timings.last = now;
+        }
+        if self.options.coalesce {
+            return;
+        }
+        let entered = span
+            .extensions_mut()
+            .remove::<EnteredAt>()
+            .map(|EnteredAt(entered)| entered);
+        let now = self.options.backend.now();
+        let duration = match (entered, now) {
+            (Some(entered), Some(now)) if self.options.duration_in_name => Some(now - entered),
+            _ => None,
+        };
+        let mark_measure_name = self.span_measure_name(&span, duration);
+        if let (Some(entered), Some(now)) = (entered, now) {
+            self.report_measure(&span, &mark_measure_name, entered, now);
+        }
+        let measure_details = self.make_measure_details(&span);
+        if self.measures_by_timestamp() {
+            if let (Some(entered), Some(now)) = (entered, now) {
+                // Ignore errors
+                let _ = self.options.backend.measure_between(
+                    &mark_measure_name,
+                    entered,
+                    now,
+                    measure_details.as_ref(),
+                );
+            }
+            return;
+        }
         let mark_enter_name = self.span_enter_name(&span);
         let mark_exit_name = self.span_exit_name(&span);
-        let mark_measure_name = self.span_measure_name(&span);
-        let _ = PERF.with(|p| {
-            if let Some(details) = self.fmt_details.find_details(&span.extensions()) {
-                p.mark_detailed(&mark_exit_name, details)?;
-                p.measure_detailed(
+        let details = self.make_mark_details(&span);
+        let backend = &self.options.backend;
+        // Ignore errors
+        let _ = backend
+            .mark(&mark_exit_name, details.as_ref())
+            .and_then(|()| {
+                backend.measure(
                     &mark_measure_name,
                     &mark_enter_name,
-                    &mark_exit_name,
-                    details,
-                )?;
-            } else {
-                p.mark(&mark_exit_name)?;
-                p.measure(&mark_measure_name, &mark_enter_name, &mark_exit_name)?;
+                    Some(&mark_exit_name),
+                    measure_details.as_ref(),
+                )
+            });
+    }
+    fn on_follows_from(&self, span: &span::Id, follows: &span::Id, ctx: Context<'_, S>) {
+        if !self.options.follows_from {
+            return;
+        }
+        let span = ctx.span(span).expect("can't find span, this is a bug");
+        if !self.is_sampled(&span) {
+            return;
+        }
+        let name = ctx.span(follows).map(|follows| follows.metadata().name());
+        let link = (follows.into_u64(), name);
+        let mut ext = span.extensions_mut();
+        match ext.get_mut::<FollowsFrom>() {
+            Some(FollowsFrom(links)) => links.push(link),
+            None => ext.insert(FollowsFrom(vec![link])),
+        }
+    }
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        let span = ctx.span(&id).expect("can't find span, this is a bug");
+        let first_entered = span.extensions_mut().remove::<FirstEnteredAt>();
+        if let Some(FirstEnteredAt(first_entered)) = first_entered {
+            if let Some(open_spans) = &self.options.open_spans {
+                open_spans.remove(&id);
+            }
+            let measure_name = self.span_measure_name(&span, None);
+            if let Some(now) = self.options.backend.now() {
+                self.report_measure(&span, &measure_name, first_entered, now);
+                let details = self.make_measure_details(&span);
+                // Ignore errors
+                let _ = self.options.backend.measure_between(
+                    &measure_name,
+                    first_entered,
+                    now,
+                    details.as_ref(),
+                );
             }
-            Result::<(), JsValue>::Ok(())
-        }); // Ignore errors
+        }
+        let timings = match span.extensions_mut().remove::<SpanTimings>() {
+            Some(timings) => timings,
+            None => return,
+        };
+        let now = self.options.backend.now().unwrap_or(timings.last);
+        let idle = timings.idle + (now - timings.last);
+        let details = self.structured_measure_details(&span);
+        let busy_prop = JsString::from(wasm_bindgen::intern(self.options.detail_keys.busy));
+        let idle_prop = JsString::from(wasm_bindgen::intern(self.options.detail_keys.idle));
+        Reflect::set(&details, &busy_prop, &JsValue::from(timings.busy)).unwrap();
+        Reflect::set(&details, &idle_prop, &JsValue::from(idle)).unwrap();
+        let details = self.finish_details(details, &span);
+
+        let measure_name = self.span_lifetime_name(&span);
+        // Ignore errors
+        let _ = self.options.backend.measure_between(
+            &measure_name,
+            timings.created,
+            now,
+            Some(&details),
+        );
+    }
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if !self.options.event_timestamps && !self.options.event_marks {
+            return;
+        }
+        let mut visitor = EventVisitor::default();
+        event.record(&mut visitor);
+        let message = visitor
+            .message
+            .unwrap_or_else(|| event.metadata().name().to_owned());
+        let prefix = match &self.options.namespace {
+            Some(namespace) => format!("{namespace}:"),
+            None => String::new(),
+        };
+        if self.options.event_timestamps {
+            web_sys::console::time_stamp_with_data(&JsValue::from(format!("{prefix}{message}")));
+        }
+        if self.options.event_marks {
+            let mut details = vec![];
+            if let Some(scope) = ctx.event_scope(event) {
+                for span in scope.take(self.options.inherit_depth) {
+                    if let Some(span_details) = self.fmt_details.find_details(&span.extensions()) {
+                        if !span_details.is_empty() {
+                            details.push(span_details.to_owned());
+                        }
+                    }
+                }
+            }
+            details.reverse();
+            if !visitor.fields.is_empty() {
+                details.push(visitor.fields);
+            }
+            let span = ctx
+                .event_scope(event)
+                .and_then(|mut scope| scope.next())
+                .filter(|_| self.options.event_span_names);
+            let mark_name = match span {
+                Some(span) => self.template_name(&span, &format!("event: {message}")),
+                None => format!("{prefix}event: {message}"),
+            };
+            let details = (!details.is_empty()).then(|| JsValue::from(details.join(" ")));
+            // Ignore errors
+            let _ = self.options.backend.mark(&mark_name, details.as_ref());
+        }
     }
     fn on_id_change(&self, _: &span::Id, _: &span::Id, _ctx: Context<'_, S>) {
         web_sys::console::warn_1(&JsValue::from(
@@ -205,14 +1794,202 @@ where
 
 /// Construct a new layer recording performance events.
 ///
-/// The default will not attach any additional field information to the events.
+/// The default will not attach any additional field information to the events. This is a shortcut for building a
+/// default [`PerformanceLayerBuilder`].
+///
+/// Without details, this is the cheapest way to record spans: marks and measures are emitted by name only, the
+/// fields of spans are never visited, and only the zero-sized [`PerfSampled`] marker is stored in their extensions.
+/// Options that read fields, such as [`with_name_key`](PerformanceEventsLayer::with_name_key), or attach other
+/// details, such as [`with_parent_info`](PerformanceEventsLayer::with_parent_info), add their own cost on top.
 pub fn performance_layer<S>() -> PerformanceEventsLayer<S, ()>
 where
     S: Subscriber + for<'lookup> LookupSpan<'lookup>,
 {
-    PerformanceEventsLayer {
-        fmt_details: (),
-        _inner: PhantomData,
+    PerformanceLayerBuilder::new().build()
+}
+
+/// A builder accumulating the configuration of a [`PerformanceEventsLayer`], until the layer is [built](Self::build).
+///
+/// The builder has a method for each option of the layer, with the same name and meaning. This keeps the
+/// configuration in one place when it is assembled step by step, e.g. depending on the environment, and makes it
+/// easy to hand around and test before any layer exists.
+///
+/// ```rust, no_run
+/// use tracing_subscriber::fmt::format::DefaultFields;
+/// use tracing_web::PerformanceLayerBuilder;
+/// # use tracing_subscriber::Registry;
+///
+/// let mut builder = PerformanceLayerBuilder::<Registry>::new()
+///     .with_namespace("my-app")
+///     .with_sampling(0.1);
+/// if cfg!(debug_assertions) {
+///     builder = builder.with_parent_info();
+/// }
+/// let perf_layer = builder
+///     .with_details_from_fields(DefaultFields::new())
+///     .build();
+/// ```
+pub struct PerformanceLayerBuilder<S, N = ()> {
+    layer: PerformanceEventsLayer<S, N>,
+}
+
+impl<S> PerformanceLayerBuilder<S, ()> {
+    /// Create a builder with the default configuration, see [`performance_layer`].
+    pub fn new() -> Self {
+        Self {
+            layer: PerformanceEventsLayer {
+                fmt_details: (),
+                options: LayerOptions::default(),
+                _inner: PhantomData,
+            },
+        }
+    }
+}
+
+impl<S> Default for PerformanceLayerBuilder<S, ()> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Defines builder methods passing their arguments on to the method of the same name of the layer being built.
+macro_rules! forward_options {
+    ($($(#[$attr:meta])* fn $name:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(
+            $(#[$attr])*
+            pub fn $name(mut self, $($arg: $ty),*) -> Self {
+                self.layer = self.layer.$name($($arg),*);
+                self
+            }
+        )*
+    };
+}
+
+impl<S, N> PerformanceLayerBuilder<S, N> {
+    /// Build the layer with the accumulated configuration.
+    pub fn build(self) -> PerformanceEventsLayer<S, N> {
+        self.layer
+    }
+    /// See [`PerformanceEventsLayer::with_details_from_fields`].
+    pub fn with_details_from_fields<N2>(
+        self,
+        fmt_fields: N2,
+    ) -> PerformanceLayerBuilder<S, FormatSpanFromFields<N2>>
+    where
+        N2: 'static + for<'writer> FormatFields<'writer>,
+    {
+        PerformanceLayerBuilder {
+            layer: self.layer.with_details_from_fields(fmt_fields),
+        }
+    }
+    /// See [`PerformanceEventsLayer::with_details`].
+    pub fn with_details<N2: FormatSpan>(self, fmt_details: N2) -> PerformanceLayerBuilder<S, N2> {
+        PerformanceLayerBuilder {
+            layer: self.layer.with_details(fmt_details),
+        }
+    }
+    /// See [`PerformanceEventsLayer::without_details`].
+    pub fn without_details(self) -> PerformanceLayerBuilder<S, ()> {
+        PerformanceLayerBuilder {
+            layer: self.layer.without_details(),
+        }
+    }
+    /// See [`PerformanceEventsLayer::with_detail_augment`].
+    pub fn with_detail_augment<F>(mut self, augment: F) -> Self
+    where
+        F: 'static + Fn(&mut Object, &SpanRef<'_, S>) + Send + Sync,
+    {
+        self.layer = self.layer.with_detail_augment(augment);
+        self
+    }
+    /// See [`PerformanceEventsLayer::with_measure_callback`].
+    pub fn with_measure_callback<F>(mut self, callback: F) -> Self
+    where
+        F: 'static + Fn(&str, f64, f64, Option<&str>) + Send + Sync,
+    {
+        self.layer = self.layer.with_measure_callback(callback);
+        self
+    }
+    /// See [`PerformanceEventsLayer::with_measure_sink`].
+    pub fn with_measure_sink<F>(mut self, sink: F) -> Self
+    where
+        F: 'static + FnMut(Measure) + Send,
+    {
+        self.layer = self.layer.with_measure_sink(sink);
+        self
+    }
+    forward_options! {
+        /// See [`PerformanceEventsLayer::with_namespace`].
+        fn with_namespace(namespace: impl Into<String>);
+        /// See [`PerformanceEventsLayer::with_name_key`].
+        fn with_name_key(field: &'static str);
+        /// See [`PerformanceEventsLayer::with_busy_time`].
+        fn with_busy_time();
+        /// See [`PerformanceEventsLayer::with_parent_info`].
+        fn with_parent_info();
+        /// See [`PerformanceEventsLayer::with_sequence_numbers`].
+        fn with_sequence_numbers();
+        /// See [`PerformanceEventsLayer::with_sampling`].
+        fn with_sampling(ratio: f64);
+        /// See [`PerformanceEventsLayer::with_sampling_seed`].
+        fn with_sampling_seed(seed: u64);
+        /// See [`PerformanceEventsLayer::with_sample_field`].
+        fn with_sample_field(field: &'static str);
+        /// See [`PerformanceEventsLayer::with_create_marks`].
+        fn with_create_marks(enabled: bool);
+        /// See [`PerformanceEventsLayer::with_duration_in_name`].
+        fn with_duration_in_name();
+        /// See [`PerformanceEventsLayer::with_event_timestamps`].
+        fn with_event_timestamps();
+        /// See [`PerformanceEventsLayer::with_event_marks`].
+        fn with_event_marks();
+        /// See [`PerformanceEventsLayer::with_span_event_marks`].
+        fn with_span_event_marks();
+        /// See [`PerformanceEventsLayer::with_inherited_fields`].
+        fn with_inherited_fields(depth: usize);
+        /// See [`PerformanceEventsLayer::with_timestamp_measures`].
+        fn with_timestamp_measures();
+        /// See [`PerformanceEventsLayer::with_record_marks`].
+        fn with_record_marks(enabled: bool);
+        /// See [`PerformanceEventsLayer::with_marks`].
+        fn with_marks(enabled: bool);
+        /// See [`PerformanceEventsLayer::with_color_field`].
+        fn with_color_field(field: &'static str);
+        /// See [`PerformanceEventsLayer::with_category_field`].
+        fn with_category_field(field: &'static str);
+        /// See [`PerformanceEventsLayer::with_build_id`].
+        fn with_build_id(build_id: &str);
+        /// See [`PerformanceEventsLayer::with_propagation_field`].
+        fn with_propagation_field(field: &'static str);
+        /// See [`PerformanceEventsLayer::with_memory_sampling`].
+        fn with_memory_sampling(enabled: bool);
+        /// See [`PerformanceEventsLayer::with_lanes`].
+        fn with_lanes(enabled: bool);
+        /// See [`PerformanceEventsLayer::with_follows_from`].
+        fn with_follows_from(enabled: bool);
+        /// See [`PerformanceEventsLayer::with_nested_tracks`].
+        fn with_nested_tracks(enabled: bool);
+        /// See [`PerformanceEventsLayer::with_span_id_token`].
+        fn with_span_id_token(enabled: bool);
+        /// See [`PerformanceEventsLayer::with_detail_keys`].
+        fn with_detail_keys(keys: DetailKeys);
+        /// See [`PerformanceEventsLayer::with_coalesced_measures`].
+        fn with_coalesced_measures();
+        /// See [`PerformanceEventsLayer::with_open_span_tracking`].
+        fn with_open_span_tracking();
+        /// See [`PerformanceEventsLayer::with_backend`].
+        fn with_backend(backend: impl PerfBackend);
+    }
+}
+
+impl<S, N> PerformanceLayerBuilder<S, FormatSpanFromFields<N>> {
+    /// See [`PerformanceEventsLayer::with_reused_fields`].
+    pub fn with_reused_fields<R: 'static>(
+        self,
+    ) -> PerformanceLayerBuilder<S, FormatSpanFromFields<N, R>> {
+        PerformanceLayerBuilder {
+            layer: self.layer.with_reused_fields::<R>(),
+        }
     }
 }
 
@@ -238,18 +2015,62 @@ impl FormatSpan for () {
     fn record_values(&self, _: &mut ExtensionsMut<'_>, _: &span::Record<'_>) {}
 }
 
+impl<S, N> PerformanceEventsLayer<S, FormatSpanFromFields<N>> {
+    /// Reuse the fields formatted by a fmt layer with the field formatter `R`, instead of formatting them again.
+    ///
+    /// The formatted fields are shared between layers as a [`FormattedFields`] extension of the span, which is
+    /// specific to the field formatter. So by default, the fields are only formatted once if the fmt layer uses the
+    /// same formatter as given to [`with_details_from_fields`](Self::with_details_from_fields), e.g.
+    /// [`DefaultFields`] for both. If they differ, e.g. when the fmt layer uses [`Pretty`], this makes the details
+    /// use the fields as formatted for the fmt layer, whenever they are available.
+    ///
+    /// ```rust, no_run
+    /// use tracing_subscriber::fmt::format::{DefaultFields, Pretty};
+    /// # use tracing_subscriber::Registry;
+    ///
+    /// let perf_layer = tracing_web::performance_layer::<Registry>()
+    ///     .with_details_from_fields(DefaultFields::new())
+    ///     .with_reused_fields::<Pretty>();
+    /// ```
+    ///
+    /// The fields are formatted with the layer's own formatter only for spans not seen by the fmt layer, e.g. because
+    /// they are filtered out for it.
+    ///
+    /// [`DefaultFields`]: tracing_subscriber::fmt::format::DefaultFields
+    /// [`Pretty`]: tracing_subscriber::fmt::format::Pretty
+    pub fn with_reused_fields<R: 'static>(
+        self,
+    ) -> PerformanceEventsLayer<S, FormatSpanFromFields<N, R>> {
+        PerformanceEventsLayer {
+            fmt_details: FormatSpanFromFields {
+                inner: self.fmt_details.inner,
+                _reuse: PhantomData,
+            },
+            options: self.options,
+            _inner: PhantomData,
+        }
+    }
+}
+
 /// An adaptor for Formatters from [`mod@tracing_subscriber::fmt::format`] as a [`FormatSpan`].
 ///
 /// Uses [`FormattedFields`] to store the details attachement, so it might reuse an existing extension
-/// for logging, to save some work visiting the recorded fields.
-pub struct FormatSpanFromFields<N> {
+/// for logging, to save some work visiting the recorded fields. The extension formatted by `R` is preferred, see
+/// [`PerformanceEventsLayer::with_reused_fields`].
+pub struct FormatSpanFromFields<N, R = N> {
     inner: N,
+    _reuse: PhantomData<fn(R)>,
 }
-impl<N> FormatSpanFromFields<N>
+impl<N, R> FormatSpanFromFields<N, R>
 where
     N: 'static + for<'writer> FormatFields<'writer>,
+    R: 'static,
 {
     fn add_formatted_fields(&self, ext: &mut ExtensionsMut<'_>, fields: impl RecordFields) {
+        // Fast path, the fields have already been formatted by another layer
+        if ext.get_mut::<FormattedFields<R>>().is_some() {
+            return;
+        }
         if ext.get_mut::<FormattedFields<N>>().is_none() {
             let mut fmt_fields = FormattedFields::<N>::new(String::new());
             if self
@@ -263,23 +2084,32 @@ where
     }
 }
 
-impl<N> FormatSpan for FormatSpanFromFields<N>
+impl<N, R> FormatSpan for FormatSpanFromFields<N, R>
 where
     N: 'static + for<'writer> FormatFields<'writer>,
+    R: 'static,
 {
     fn find_details<'ext>(&self, ext: &'ext Extensions<'_>) -> Option<&'ext str> {
-        let fields = ext.get::<FormattedFields<N>>()?;
-        Some(&fields.fields)
+        // Spans without fields have no details, so that marks and measures are emitted without any
+        let fields = match ext.get::<FormattedFields<R>>() {
+            Some(fields) => &fields.fields,
+            None => &ext.get::<FormattedFields<N>>()?.fields,
+        };
+        Some(fields.as_str()).filter(|fields| !fields.is_empty())
     }
 
     fn add_details(&self, ext: &mut ExtensionsMut<'_>, attrs: &span::Attributes<'_>) {
+        // Nothing to format, fields recorded later are formatted in `record_values`
+        if attrs.values().is_empty() {
+            return;
+        }
         self.add_formatted_fields(ext, attrs);
     }
 
     fn record_values(&self, ext: &mut ExtensionsMut<'_>, values: &span::Record<'_>) {
         if let Some(fields) = ext.get_mut::<FormattedFields<N>>() {
             let _ = self.inner.add_fields(fields, values);
-        } else {
+        } else if ext.get_mut::<FormattedFields<R>>().is_none() {
             self.add_formatted_fields(ext, values);
         }
     }
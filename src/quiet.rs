@@ -0,0 +1,140 @@
+use std::{cell::RefCell, collections::HashMap, marker::PhantomData};
+
+use tracing_core::{span, Level, Subscriber};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+use crate::console_writer::{LogDispatcher, LogOptions};
+
+/// A [`Layer`] that holds back verbose console output within spans, and only shows it if an error occurs.
+///
+/// While a span is entered, lines written by [`MakeWebConsoleWriter`] at the held level or more verbose, by default
+/// DEBUG and TRACE, are held back. If an ERROR event is written within the span, the held lines are shown right
+/// before it, as context of the error, and all further lines are shown as usual until the span closes. Otherwise,
+/// the held lines are discarded when the span closes. Lines are kept while the span is exited in between, e.g. while
+/// an instrumented future is pending, so that an error in a later poll still shows the context of earlier ones.
+///
+/// [`MakeWebConsoleWriter`]: crate::MakeWebConsoleWriter
+pub struct QuietLayer<S> {
+    held_level: Level,
+    _inner: PhantomData<fn(S)>,
+}
+
+/// Construct a new layer holding back verbose console output unless an error occurs.
+pub fn quiet_layer<S>() -> QuietLayer<S>
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    QuietLayer {
+        held_level: Level::DEBUG,
+        _inner: PhantomData,
+    }
+}
+
+impl<S> QuietLayer<S> {
+    /// Choose the level of lines that are held back, together with all more verbose levels.
+    ///
+    /// For example, with [`Level::INFO`], INFO, DEBUG and TRACE lines are held back.
+    pub fn with_held_level(mut self, level: Level) -> Self {
+        self.held_level = level;
+        self
+    }
+}
+
+/// A line of console output held back until it is known whether an error occurs.
+pub(crate) struct HeldLine {
+    pub(crate) log: LogDispatcher,
+    pub(crate) level: Level,
+    pub(crate) message: String,
+    pub(crate) options: LogOptions,
+}
+
+struct QuietFrame {
+    id: span::Id,
+    held_level: Level,
+}
+
+#[derive(Default)]
+struct HeldLines {
+    error_seen: bool,
+    lines: Vec<HeldLine>,
+}
+
+thread_local! {
+    // The currently entered spans on this thread.
+    static FRAMES: RefCell<Vec<QuietFrame>> = RefCell::new(Vec::new());
+    // The lines held back within each span until it closes. These can't go into the span extensions, which have to
+    // be `Send + Sync`, while the lines may carry `JsValue`s.
+    static HELD: RefCell<HashMap<span::Id, HeldLines>> = RefCell::new(HashMap::new());
+}
+
+/// Hold back the line, if it is too verbose to show within the innermost span. Otherwise, the line is returned.
+pub(crate) fn hold_line(line: HeldLine) -> Option<HeldLine> {
+    let (id, held_level) = FRAMES.with(|frames| {
+        let frames = frames.borrow();
+        let frame = frames.last()?;
+        Some((frame.id.clone(), frame.held_level))
+    })?;
+    // Note that more verbose levels compare greater
+    if line.level < held_level {
+        return Some(line);
+    }
+    HELD.with(|held| {
+        let mut held = held.borrow_mut();
+        let held = held.entry(id).or_default();
+        if held.error_seen {
+            return Some(line);
+        }
+        held.lines.push(line);
+        None
+    })
+}
+
+/// Take the lines held back in all entered spans, outermost first, to be shown before an error.
+///
+/// All entered spans stop holding back lines until they close.
+pub(crate) fn take_held_lines() -> Vec<HeldLine> {
+    let ids: Vec<span::Id> = FRAMES.with(|frames| {
+        frames
+            .borrow()
+            .iter()
+            .map(|frame| frame.id.clone())
+            .collect()
+    });
+    HELD.with(|held| {
+        let mut held = held.borrow_mut();
+        let mut lines = vec![];
+        for id in ids {
+            let held = held.entry(id).or_default();
+            held.error_seen = true;
+            lines.append(&mut held.lines);
+        }
+        lines
+    })
+}
+
+impl<S> Layer<S> for QuietLayer<S>
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    fn on_enter(&self, id: &span::Id, _ctx: Context<'_, S>) {
+        FRAMES.with(|frames| {
+            frames.borrow_mut().push(QuietFrame {
+                id: id.clone(),
+                held_level: self.held_level,
+            })
+        });
+    }
+    fn on_exit(&self, id: &span::Id, _ctx: Context<'_, S>) {
+        // The held lines are kept until the span closes, the span might be entered again
+        FRAMES.with(|frames| {
+            let mut frames = frames.borrow_mut();
+            if let Some(pos) = frames.iter().rposition(|frame| &frame.id == id) {
+                frames.remove(pos);
+            }
+        });
+    }
+    fn on_close(&self, id: span::Id, _ctx: Context<'_, S>) {
+        // Drop the discarded lines outside of the borrow, in case dropping a value logs
+        let _discarded = HELD.with(|held| held.borrow_mut().remove(&id));
+    }
+}
@@ -0,0 +1,77 @@
+use std::fmt::{self, Write};
+
+use tracing_core::field::{Field, Visit};
+use tracing_subscriber::{
+    field::RecordFields,
+    fmt::{format::Writer, FormatFields},
+};
+
+/// A [`FormatFields`] that writes fields sorted alphabetically by name, instead of in the order they are recorded.
+///
+/// This makes the output deterministic, e.g. to diff logs across runs. It can be used for the fields of spans in the
+/// details of performance events, as well as for the fmt layer:
+///
+/// ```rust, no_run
+/// use tracing_web::{performance_layer, SortedFields};
+/// # use tracing_subscriber::Registry;
+///
+/// let fmt_layer = tracing_subscriber::fmt::layer::<Registry>().fmt_fields(SortedFields::new());
+/// let perf_layer = performance_layer::<Registry>().with_details_from_fields(SortedFields::new());
+/// ```
+///
+/// Fields are written as `name=value`, with the message, if any, in front. Note that fields recorded later, e.g.
+/// with [`Span::record`], are appended, and only sorted among themselves.
+///
+/// [`Span::record`]: https://docs.rs/tracing/latest/tracing/struct.Span.html#method.record
+#[derive(Debug, Clone, Default)]
+pub struct SortedFields {
+    _private: (),
+}
+
+impl SortedFields {
+    /// Create a new field formatter sorting fields by name.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+// Collects the message and the formatted values of all other fields, to be sorted once all have been recorded.
+#[derive(Default)]
+struct SortingVisitor {
+    message: Option<String>,
+    fields: Vec<(&'static str, String)>,
+}
+
+impl Visit for SortingVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = Some(format!("{:?}", value));
+        } else {
+            self.fields.push((field.name(), format!("{:?}", value)));
+        }
+    }
+}
+
+impl<'writer> FormatFields<'writer> for SortedFields {
+    fn format_fields<R: RecordFields>(
+        &self,
+        mut writer: Writer<'writer>,
+        fields: R,
+    ) -> fmt::Result {
+        let mut visitor = SortingVisitor::default();
+        fields.record(&mut visitor);
+        // A stable sort keeps the recording order of repeated names
+        visitor.fields.sort_by_key(|&(name, _)| name);
+
+        let mut separator = "";
+        if let Some(message) = &visitor.message {
+            writer.write_str(message)?;
+            separator = " ";
+        }
+        for (name, value) in &visitor.fields {
+            write!(writer, "{separator}{name}={value}")?;
+            separator = " ";
+        }
+        Ok(())
+    }
+}
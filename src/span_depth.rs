@@ -0,0 +1,136 @@
+use std::{cell::RefCell, marker::PhantomData};
+
+use tracing_core::{span, Subscriber};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+use crate::performance_layer::performance_now;
+
+thread_local! {
+    // The currently entered spans on this thread, with their depth in the span tree, their name and, if recorded,
+    // the time they were first entered.
+    static ENTERED: RefCell<Vec<(span::Id, usize, &'static str, Option<f64>)>> = RefCell::new(Vec::new());
+}
+
+// Extension recording the time from `performance.now()` a span was first entered.
+struct FirstEntered(f64);
+
+/// A [`Layer`] keeping track of the depth of the currently entered span.
+///
+/// This is a companion layer for [`MakeWebConsoleWriter::with_pretty_depth`] and
+/// [`MakeWebConsoleWriter::with_breadcrumbs`], since a [`MakeWriter`] does not have access to the span context of the
+/// event it writes. It does not produce any output by itself.
+///
+/// [`MakeWebConsoleWriter::with_pretty_depth`]: crate::MakeWebConsoleWriter::with_pretty_depth
+/// [`MakeWebConsoleWriter::with_breadcrumbs`]: crate::MakeWebConsoleWriter::with_breadcrumbs
+/// [`MakeWriter`]: tracing_subscriber::fmt::MakeWriter
+pub struct SpanDepthLayer<S> {
+    elapsed: bool,
+    _inner: PhantomData<fn(S)>,
+}
+
+/// Construct a new layer keeping track of the depth of the currently entered span.
+pub fn span_depth_layer<S>() -> SpanDepthLayer<S>
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    SpanDepthLayer {
+        elapsed: false,
+        _inner: PhantomData,
+    }
+}
+
+impl<S> SpanDepthLayer<S>
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    /// Record the time each span is first entered, for [`MakeWebConsoleWriter::with_span_elapsed`].
+    ///
+    /// The time is taken from `performance.now()` and stored in the extensions of the span, so that re-entering a
+    /// span, e.g. when polling a future, does not reset it. If the Performance API is not available, nothing is
+    /// recorded.
+    ///
+    /// [`MakeWebConsoleWriter::with_span_elapsed`]: crate::MakeWebConsoleWriter::with_span_elapsed
+    pub fn with_elapsed_time(mut self) -> Self {
+        self.elapsed = true;
+        self
+    }
+}
+
+impl<S> Layer<S> for SpanDepthLayer<S>
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, S>) {
+        let span = ctx.span(id);
+        let (depth, name) = span.as_ref().map_or((0, ""), |span| {
+            (span.scope().count(), span.metadata().name())
+        });
+        let first_entered = span.filter(|_| self.elapsed).and_then(|span| {
+            let mut ext = span.extensions_mut();
+            if let Some(FirstEntered(time)) = ext.get_mut::<FirstEntered>() {
+                return Some(*time);
+            }
+            let time = performance_now()?;
+            ext.insert(FirstEntered(time));
+            Some(time)
+        });
+        ENTERED.with(|entered| {
+            entered
+                .borrow_mut()
+                .push((id.clone(), depth, name, first_entered))
+        });
+    }
+    fn on_exit(&self, id: &span::Id, _ctx: Context<'_, S>) {
+        ENTERED.with(|entered| {
+            let mut entered = entered.borrow_mut();
+            // Spans are usually exited in reverse order of entering, but search for the id to be robust
+            if let Some(pos) = entered
+                .iter()
+                .rposition(|(entered_id, _, _, _)| entered_id == id)
+            {
+                entered.remove(pos);
+            }
+        });
+    }
+}
+
+/// The depth of the innermost span entered on the current thread, or `0` if there is none.
+pub(crate) fn current_span_depth() -> usize {
+    ENTERED.with(|entered| entered.borrow().last().map_or(0, |&(_, depth, _, _)| depth))
+}
+
+/// The innermost span entered on the current thread, if any.
+pub(crate) fn current_span_id() -> Option<span::Id> {
+    ENTERED.with(|entered| entered.borrow().last().map(|(id, _, _, _)| id.clone()))
+}
+
+/// The time in milliseconds since the innermost span entered on the current thread was first entered, if recorded.
+pub(crate) fn current_span_elapsed() -> Option<f64> {
+    let first_entered =
+        ENTERED.with(|entered| entered.borrow().last().and_then(|&(_, _, _, time)| time))?;
+    Some(performance_now()? - first_entered)
+}
+
+/// The names of the spans entered on the current thread, joined by `separator` in the given order.
+pub(crate) fn current_span_path(separator: &str, order: BreadcrumbOrder) -> String {
+    ENTERED.with(|entered| {
+        let entered = entered.borrow();
+        let names = entered.iter().map(|&(_, _, name, _)| name);
+        let names: Vec<_> = match order {
+            BreadcrumbOrder::RootFirst => names.collect(),
+            BreadcrumbOrder::LeafFirst => names.rev().collect(),
+        };
+        names.join(separator)
+    })
+}
+
+/// The order of the span names in a breadcrumb trail, see
+/// [`MakeWebConsoleWriter::with_breadcrumbs`](crate::MakeWebConsoleWriter::with_breadcrumbs).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BreadcrumbOrder {
+    /// Start with the outermost span, e.g. `app > page > widget`.
+    #[default]
+    RootFirst,
+    /// Start with the innermost span, e.g. `widget < page < app`.
+    LeafFirst,
+}
@@ -0,0 +1,116 @@
+use std::{fmt, marker::PhantomData};
+
+use js_sys::Date;
+use tracing_core::{
+    field::{Field, Visit},
+    span, Subscriber,
+};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+use crate::{console_writer::log_line, performance_layer::with_performance};
+
+/// A [`Layer`] logging a single summary line to the console when a span closes.
+///
+/// The summary shows the name of the span, the time from when it was first entered until it closed, and the final
+/// values of its fields, e.g. `fetch_user closed after 12.3ms id=42 status=200`. It is logged with the console
+/// method for the level of the span. Together with a filter that drops the events of a target, this gives a concise
+/// log per operation, without the noise of the individual events.
+pub struct ConsoleSpanSummaryLayer<S> {
+    pretty: bool,
+    _inner: PhantomData<fn(S)>,
+}
+
+/// Construct a new layer logging a summary line for each span when it closes.
+pub fn console_span_summary_layer<S>() -> ConsoleSpanSummaryLayer<S>
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    ConsoleSpanSummaryLayer {
+        pretty: false,
+        _inner: PhantomData,
+    }
+}
+
+impl<S> ConsoleSpanSummaryLayer<S> {
+    /// Show a label for the level of the span in front of each summary, as with
+    /// [`MakeWebConsoleWriter::with_pretty_level`].
+    ///
+    /// [`MakeWebConsoleWriter::with_pretty_level`]: crate::MakeWebConsoleWriter::with_pretty_level
+    pub fn with_pretty_level(mut self) -> Self {
+        self.pretty = true;
+        self
+    }
+}
+
+// The state of a span needed for its summary, stored in its extensions.
+struct SpanSummary {
+    started: f64,
+    entered: bool,
+    fields: Vec<(&'static str, String)>,
+}
+
+impl Visit for SpanSummary {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record_debug(field, &format_args!("{}", value));
+    }
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        // Values recorded later replace earlier ones in the summary, but keep their position
+        let value = format!("{:?}", value);
+        match self
+            .fields
+            .iter_mut()
+            .find(|(name, _)| *name == field.name())
+        {
+            Some((_, existing)) => *existing = value,
+            None => self.fields.push((field.name(), value)),
+        }
+    }
+}
+
+fn now() -> f64 {
+    with_performance(|p| p.now()).unwrap_or_else(Date::now)
+}
+
+impl<S> Layer<S> for ConsoleSpanSummaryLayer<S>
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let span = ctx.span(id).expect("can't find span, this is a bug");
+        let mut summary = SpanSummary {
+            started: now(),
+            entered: false,
+            fields: Vec::new(),
+        };
+        attrs.record(&mut summary);
+        span.extensions_mut().insert(summary);
+    }
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        let span = ctx.span(id).expect("can't find span, this is a bug");
+        if let Some(summary) = span.extensions_mut().get_mut::<SpanSummary>() {
+            values.record(summary);
+        }
+    }
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, S>) {
+        let span = ctx.span(id).expect("can't find span, this is a bug");
+        if let Some(summary) = span.extensions_mut().get_mut::<SpanSummary>() {
+            if !summary.entered {
+                summary.entered = true;
+                summary.started = now();
+            }
+        }
+    }
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        let span = ctx.span(&id).expect("can't find span, this is a bug");
+        let summary = match span.extensions_mut().remove::<SpanSummary>() {
+            Some(summary) => summary,
+            None => return,
+        };
+        let elapsed = now() - summary.started;
+        let mut message = format!("{} closed after {:.1}ms", span.metadata().name(), elapsed);
+        for (name, value) in &summary.fields {
+            message.push_str(&format!(" {name}={value}"));
+        }
+        log_line(*span.metadata().level(), message, self.pretty);
+    }
+}
@@ -0,0 +1,252 @@
+use std::{fmt, marker::PhantomData};
+
+use js_sys::{Array, Date, Map, Object, Reflect, JSON};
+use tracing_core::{
+    field::{Field, Visit},
+    Event, Metadata, Subscriber,
+};
+use tracing_subscriber::{layer::Context, Layer};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::console;
+
+use crate::performance_layer::span_id_token;
+
+/// A [`Layer`] that logs each event as a single, structured object to the [`console`].
+///
+/// Each event results in exactly one `console.log(object)` call, where the object has the shape
+///
+/// ```js
+/// { level, message, target, timestamp, fields: { ... } }
+/// ```
+///
+/// This is useful for tools that hook `console.log`, such as the breadcrumb capture of error reporting services,
+/// which prefer a single argument they can serialize. The `timestamp` is given in milliseconds since the unix epoch,
+/// as returned by `Date.now()`.
+///
+/// Use this instead of, not in addition to, a `fmt` layer writing to the console, unless you want to see each event
+/// twice.
+pub struct StructuredConsoleLayer<S> {
+    dir_field: Option<&'static str>,
+    field_groups: bool,
+    span_id: bool,
+    fields_map: bool,
+    _inner: PhantomData<fn(S)>,
+}
+
+impl<S> StructuredConsoleLayer<S> {
+    /// Reserve a field to be inspected with `console.dir`.
+    ///
+    /// When an event carries the given field, its value is removed from the `fields` and passed to an additional
+    /// `console.dir` call after logging the event, which shows the prototype-aware object inspector.
+    ///
+    /// Since tracing fields can not hold a [`JsValue`], the value is expected to be recorded as a JSON string, which
+    /// is parsed back into an object before being inspected. Values that are not valid JSON are inspected as plain
+    /// strings.
+    ///
+    /// ```rust, ignore
+    /// tracing::info!(state = %serde_json::to_string(&state)?, "updated state");
+    /// ```
+    pub fn with_dir_field(mut self, field: &'static str) -> Self {
+        self.dir_field = Some(field);
+        self
+    }
+    /// Log each event as a group titled with its message, with each field on its own line in the group.
+    ///
+    /// This is easier to read than a single object for events with many fields. Events without fields are logged as
+    /// a single line. Note that this replaces the single `console.log(object)` call per event.
+    pub fn with_field_groups(mut self) -> Self {
+        self.field_groups = true;
+        self
+    }
+    /// Log each event as its message followed by its fields as a JS `Map`, i.e. `console.log("%s %o", message, map)`.
+    ///
+    /// Unlike the plain object of the default mode, a `Map` keeps the fields in the order they are recorded, and
+    /// shows field names that are not valid identifiers as is, which is useful for fields with dynamic names. Note
+    /// that a `Map` is serialized as an empty object by `JSON.stringify`. This replaces the single
+    /// `console.log(object)` call per event, and takes precedence over [`with_field_groups`](Self::with_field_groups).
+    pub fn with_fields_as_map(mut self) -> Self {
+        self.fields_map = true;
+        self
+    }
+    /// Prefix the message of each event with the id of its enclosing span, as `[span=<id>]`.
+    ///
+    /// The id is the same as used in the names of the marks and measures of a
+    /// [`PerformanceEventsLayer`](crate::PerformanceEventsLayer), unless it identifies spans by a
+    /// [name key](crate::PerformanceEventsLayer::with_name_key). This helps to cross-reference the console with the
+    /// performance timeline, see also [`span_id_token`](crate::span_id_token). Events outside of any span are logged
+    /// unchanged.
+    pub fn with_span_id(mut self, enabled: bool) -> Self {
+        self.span_id = enabled;
+        self
+    }
+}
+
+/// Construct a new layer logging events as structured objects to the console.
+pub fn structured_console_layer<S>() -> StructuredConsoleLayer<S>
+where
+    S: Subscriber,
+{
+    StructuredConsoleLayer {
+        dir_field: None,
+        field_groups: false,
+        span_id: false,
+        fields_map: false,
+        _inner: PhantomData,
+    }
+}
+
+fn set_property(target: &Object, key: &str, value: &JsValue) {
+    let key = JsValue::from(wasm_bindgen::intern(key));
+    // Setting a property on a freshly created, plain object can not fail
+    Reflect::set(target, &key, value).unwrap();
+}
+
+// Collects the fields of an event into a js object, splitting off the message and the value to inspect.
+struct ObjectVisitor {
+    message: Option<String>,
+    fields: Object,
+    // Collects the fields instead of `fields`, if they are logged as a map
+    map: Option<Map>,
+    dir_field: Option<&'static str>,
+    dir: Option<JsValue>,
+}
+
+impl ObjectVisitor {
+    fn new(dir_field: Option<&'static str>, map: bool) -> Self {
+        Self {
+            message: None,
+            fields: Object::new(),
+            map: map.then(Map::new),
+            dir_field,
+            dir: None,
+        }
+    }
+    fn record_value(&mut self, field: &Field, value: JsValue) {
+        if self.dir_field == Some(field.name()) {
+            self.dir = Some(value);
+        } else {
+            self.set_field(field, &value);
+        }
+    }
+    fn record_text(&mut self, field: &Field, value: &str) {
+        if self.dir_field == Some(field.name()) {
+            self.dir = Some(JSON::parse(value).unwrap_or_else(|_| JsValue::from(value)));
+        } else {
+            self.set_field(field, &JsValue::from(value));
+        }
+    }
+    fn set_field(&self, field: &Field, value: &JsValue) {
+        match &self.map {
+            Some(map) => {
+                map.set(&JsValue::from(wasm_bindgen::intern(field.name())), value);
+            }
+            None => set_property(&self.fields, field.name(), value),
+        }
+    }
+}
+
+impl Visit for ObjectVisitor {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.record_value(field, JsValue::from(value));
+    }
+    // Integers are converted to js numbers instead of BigInts, since the latter can not be serialized to JSON.
+    // This is lossy for values beyond 2^53, which is acceptable for logging purposes.
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.record_value(field, JsValue::from(value as f64));
+    }
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.record_value(field, JsValue::from(value as f64));
+    }
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.record_value(field, JsValue::from(value));
+    }
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = Some(value.to_owned());
+        } else {
+            self.record_text(field, value);
+        }
+    }
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = Some(format!("{:?}", value));
+        } else {
+            self.record_text(field, &format!("{:?}", value));
+        }
+    }
+}
+
+impl<S> Layer<S> for StructuredConsoleLayer<S>
+where
+    S: Subscriber,
+{
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let meta = event.metadata();
+        let mut visitor = ObjectVisitor::new(self.dir_field, self.fields_map);
+        event.record(&mut visitor);
+
+        if self.span_id {
+            let span_id = if event.is_contextual() {
+                ctx.current_span().id().cloned()
+            } else {
+                event.parent().cloned()
+            };
+            if let Some(span_id) = span_id {
+                let message = visitor.message.take().unwrap_or_default();
+                let prefix = format!("[{}]", span_id_token(&span_id));
+                visitor.message = Some(if message.is_empty() {
+                    prefix
+                } else {
+                    format!("{prefix} {message}")
+                });
+            }
+        }
+
+        if let Some(map) = &visitor.map {
+            let fmt = JsValue::from(wasm_bindgen::intern("%s %o"));
+            let message = visitor.message.as_deref().unwrap_or_default();
+            console::log_3(&fmt, &JsValue::from(message), map);
+        } else if self.field_groups {
+            log_field_groups(meta, &visitor);
+        } else {
+            log_entry(meta, &visitor);
+        }
+        if let Some(dir) = &visitor.dir {
+            console::dir_1(dir);
+        }
+    }
+}
+
+fn log_entry(meta: &Metadata<'_>, visitor: &ObjectVisitor) {
+    let entry = Object::new();
+    set_property(&entry, "level", &JsValue::from(meta.level().as_str()));
+    set_property(
+        &entry,
+        "message",
+        &JsValue::from(visitor.message.as_deref().unwrap_or_default()),
+    );
+    set_property(&entry, "target", &JsValue::from(meta.target()));
+    set_property(&entry, "timestamp", &JsValue::from(Date::now()));
+    set_property(&entry, "fields", &visitor.fields);
+    console::log_1(&entry);
+}
+
+fn log_field_groups(meta: &Metadata<'_>, visitor: &ObjectVisitor) {
+    let title = visitor
+        .message
+        .as_deref()
+        .filter(|message| !message.is_empty())
+        .unwrap_or_else(|| meta.target());
+    let fields = Object::entries(&visitor.fields);
+    if fields.length() == 0 {
+        console::log_1(&JsValue::from(title));
+        return;
+    }
+    console::group_1(&JsValue::from(title));
+    let fmt = JsValue::from(wasm_bindgen::intern("%s: %o"));
+    for i in 0..fields.length() {
+        let field = fields.get(i).unchecked_into::<Array>();
+        console::log_3(&fmt, &field.get(0), &field.get(1));
+    }
+    console::group_end();
+}
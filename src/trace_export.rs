@@ -0,0 +1,70 @@
+use js_sys::{Array, JsString, Object, Reflect};
+use wasm_bindgen::JsValue;
+
+use crate::performance_layer::with_performance;
+
+fn get_property(target: &JsValue, key: &str) -> JsValue {
+    let key = JsString::from(wasm_bindgen::intern(key));
+    Reflect::get(target, &key).unwrap_or(JsValue::UNDEFINED)
+}
+
+fn set_property(target: &Object, key: &str, value: &JsValue) {
+    let key = JsString::from(wasm_bindgen::intern(key));
+    Reflect::set(target, &key, value).unwrap();
+}
+
+/// Export the recorded `performance.measure` entries as a trace in the Chrome trace event format.
+///
+/// Only measures emitted by a [`PerformanceEventsLayer`] configured with the given [namespace] are exported.
+/// If `namespace` is empty, all measures are exported instead.
+///
+/// The returned object can be serialized with `JSON.stringify`, saved to a file, and loaded for offline analysis in
+/// tools supporting the format, such as the Chrome devtools or [Perfetto]. Each measure becomes a complete event,
+/// with its detail, if any, attached in `args.detail`. If the Performance API is not available, the trace is empty.
+///
+/// [`PerformanceEventsLayer`]: crate::PerformanceEventsLayer
+/// [namespace]: crate::PerformanceEventsLayer::with_namespace
+/// [Perfetto]: https://ui.perfetto.dev
+pub fn export_performance_trace(namespace: &str) -> JsValue {
+    let trace_events = Array::new();
+    let prefix = format!("{namespace}:");
+    with_performance(|p| {
+        let measures = p.get_entries_by_type("measure");
+        for i in 0..measures.length() {
+            let measure = measures.get(i);
+            let name = get_property(&measure, "name")
+                .as_string()
+                .unwrap_or_default();
+            if !namespace.is_empty() && !name.starts_with(&prefix) {
+                continue;
+            }
+            // The trace event format uses microseconds, while the Performance API uses milliseconds
+            let start = get_property(&measure, "startTime").as_f64().unwrap_or(0.0);
+            let duration = get_property(&measure, "duration").as_f64().unwrap_or(0.0);
+
+            let event = Object::new();
+            set_property(&event, "name", &JsValue::from(name));
+            set_property(
+                &event,
+                "cat",
+                &JsValue::from(wasm_bindgen::intern("measure")),
+            );
+            set_property(&event, "ph", &JsValue::from(wasm_bindgen::intern("X")));
+            set_property(&event, "ts", &JsValue::from(start * 1000.0));
+            set_property(&event, "dur", &JsValue::from(duration * 1000.0));
+            set_property(&event, "pid", &JsValue::from(1));
+            set_property(&event, "tid", &JsValue::from(1));
+            let detail = get_property(&measure, "detail");
+            if !detail.is_undefined() && !detail.is_null() {
+                let args = Object::new();
+                set_property(&args, "detail", &detail);
+                set_property(&event, "args", &args);
+            }
+            trace_events.push(&event);
+        }
+    });
+
+    let trace = Object::new();
+    set_property(&trace, "traceEvents", &trace_events);
+    trace.into()
+}
@@ -0,0 +1,121 @@
+use tracing_core::{span, Event, Subscriber};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+use crate::{
+    console_group::{console_group_layer, ConsoleGroupLayer},
+    performance_layer::{performance_layer, FormatSpan, PerformanceEventsLayer},
+};
+
+/// A [`Layer`] combining a [`PerformanceEventsLayer`] and a [`ConsoleGroupLayer`].
+///
+/// Each span is then both marked in the performance timeline and grouped in the console, with options that affect
+/// both, such as the [namespace](Self::with_namespace), set in one place. This is a convenience for the common setup,
+/// the combined layers behave exactly as if they were installed separately.
+///
+/// ```rust, no_run
+/// use tracing_subscriber::{fmt::format::DefaultFields, prelude::*};
+/// use tracing_web::{web_trace_layer, MakeWebConsoleWriter};
+///
+/// let fmt_layer = tracing_subscriber::fmt::layer()
+///     .with_ansi(false)
+///     .without_time()
+///     .with_writer(MakeWebConsoleWriter::new());
+/// let trace_layer = web_trace_layer()
+///     .with_namespace("my-app")
+///     .map_performance(|layer| layer.with_details_from_fields(DefaultFields::default()));
+///
+/// tracing_subscriber::registry()
+///     .with(fmt_layer)
+///     .with(trace_layer)
+///     .init();
+/// ```
+pub struct WebTraceLayer<S, N = ()> {
+    performance: PerformanceEventsLayer<S, N>,
+    groups: ConsoleGroupLayer<S>,
+}
+
+/// Construct a new layer emitting performance events and grouping the console output of spans.
+pub fn web_trace_layer<S>() -> WebTraceLayer<S>
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    WebTraceLayer {
+        performance: performance_layer(),
+        groups: console_group_layer(),
+    }
+}
+
+impl<S, N> WebTraceLayer<S, N> {
+    /// Prefix the names of performance events and the titles of console groups with a namespace.
+    ///
+    /// See [`PerformanceEventsLayer::with_namespace`] and [`ConsoleGroupLayer::with_namespace`].
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        let namespace = namespace.into();
+        self.performance = self.performance.with_namespace(namespace.clone());
+        self.groups = self.groups.with_namespace(namespace);
+        self
+    }
+    /// Choose whether console groups are opened collapsed, see [`ConsoleGroupLayer::with_collapsed`].
+    pub fn with_collapsed(mut self, collapsed: bool) -> Self {
+        self.groups = self.groups.with_collapsed(collapsed);
+        self
+    }
+    /// Further configure the performance events, e.g. to attach details.
+    pub fn map_performance<N2>(
+        self,
+        f: impl FnOnce(PerformanceEventsLayer<S, N>) -> PerformanceEventsLayer<S, N2>,
+    ) -> WebTraceLayer<S, N2> {
+        WebTraceLayer {
+            performance: f(self.performance),
+            groups: self.groups,
+        }
+    }
+    /// Further configure the console groups.
+    pub fn map_console_groups(
+        mut self,
+        f: impl FnOnce(ConsoleGroupLayer<S>) -> ConsoleGroupLayer<S>,
+    ) -> Self {
+        self.groups = f(self.groups);
+        self
+    }
+}
+
+impl<S, N> Layer<S> for WebTraceLayer<S, N>
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+    N: FormatSpan,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        self.performance.on_new_span(attrs, id, ctx.clone());
+        self.groups.on_new_span(attrs, id, ctx);
+    }
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        self.performance.on_record(id, values, ctx.clone());
+        self.groups.on_record(id, values, ctx);
+    }
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, S>) {
+        self.performance.on_enter(id, ctx.clone());
+        self.groups.on_enter(id, ctx);
+    }
+    fn on_exit(&self, id: &span::Id, ctx: Context<'_, S>) {
+        // Mirror the order of `on_enter`
+        self.groups.on_exit(id, ctx.clone());
+        self.performance.on_exit(id, ctx);
+    }
+    fn on_follows_from(&self, id: &span::Id, follows: &span::Id, ctx: Context<'_, S>) {
+        self.performance.on_follows_from(id, follows, ctx.clone());
+        self.groups.on_follows_from(id, follows, ctx);
+    }
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        self.groups.on_close(id.clone(), ctx.clone());
+        self.performance.on_close(id, ctx);
+    }
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        self.performance.on_event(event, ctx.clone());
+        self.groups.on_event(event, ctx);
+    }
+    fn on_id_change(&self, old: &span::Id, new: &span::Id, ctx: Context<'_, S>) {
+        self.performance.on_id_change(old, new, ctx.clone());
+        self.groups.on_id_change(old, new, ctx);
+    }
+}
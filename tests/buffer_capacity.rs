@@ -0,0 +1,61 @@
+// Compares the allocations made while writing a large message in pieces, with and without a preallocated buffer.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    io::Write,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_web::MakeWebConsoleWriter;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+// Counts allocations and reallocations, to measure the churn of growing buffers.
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+const CHUNK: &[u8] = &[b'x'; 64];
+const CHUNKS: usize = 64;
+
+// The number of allocations made while writing a message of `CHUNKS` pieces, not counting the logging itself.
+fn allocations_for_message(make_writer: &MakeWebConsoleWriter) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let mut writer = make_writer.make_writer();
+    for _ in 0..CHUNKS {
+        writer.write_all(CHUNK).unwrap();
+    }
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    drop(writer);
+    allocations
+}
+
+#[wasm_bindgen_test]
+fn buffer_capacity_reduces_allocations() {
+    let growing = MakeWebConsoleWriter::new();
+    let preallocated = MakeWebConsoleWriter::new().with_buffer_capacity(CHUNK.len() * CHUNKS);
+    // Warm up, so that one-time allocations are not counted
+    allocations_for_message(&growing);
+    allocations_for_message(&preallocated);
+
+    let growing = allocations_for_message(&growing);
+    let preallocated = allocations_for_message(&preallocated);
+    assert!(growing > preallocated);
+}
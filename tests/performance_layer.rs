@@ -0,0 +1,337 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use js_sys::JSON;
+
+use tracing::{span, Subscriber};
+use tracing_subscriber::{
+    fmt::{format::DefaultFields, FormattedFields},
+    layer::Context,
+    prelude::*,
+    registry::LookupSpan,
+    Layer, Registry,
+};
+use tracing_web::{
+    is_perf_sampled, performance_layer, web_trace_layer, PerfBackend, PerformanceEventsLayer,
+    PerformanceLayerBuilder,
+};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+// Records the names of all marks and measures, and their details as JSON, with a clock advancing by one millisecond
+// per call.
+#[derive(Clone, Default)]
+struct RecordingBackend {
+    entries: Arc<Mutex<Vec<String>>>,
+    clock: Arc<Mutex<f64>>,
+    details: Arc<Mutex<Vec<String>>>,
+}
+
+impl RecordingBackend {
+    fn entries(&self) -> Vec<String> {
+        self.entries.lock().unwrap().clone()
+    }
+    fn details(&self) -> Vec<String> {
+        self.details.lock().unwrap().clone()
+    }
+    fn record_detail(&self, detail: Option<&JsValue>) {
+        if let Some(detail) = detail {
+            let json = JSON::stringify(detail)
+                .ok()
+                .and_then(|json| json.as_string())
+                .unwrap_or_default();
+            self.details.lock().unwrap().push(json);
+        }
+    }
+    fn record(&self, entry: String) -> Result<(), JsValue> {
+        self.entries.lock().unwrap().push(entry);
+        Ok(())
+    }
+}
+
+// A field value counting how often it is formatted.
+struct Counted(Arc<AtomicUsize>);
+
+impl fmt::Debug for Counted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fetch_add(1, Ordering::Relaxed);
+        f.write_str("counted")
+    }
+}
+
+// Counts the spans with details stored by the performance layer, checked when the span is entered.
+#[derive(Clone, Default)]
+struct ExtensionProbe {
+    stored: Arc<AtomicUsize>,
+}
+
+impl ExtensionProbe {
+    fn stored(&self) -> usize {
+        self.stored.load(Ordering::Relaxed)
+    }
+}
+
+impl<S> Layer<S> for ExtensionProbe
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, S>) {
+        let span = ctx.span(id).unwrap();
+        let ext = span.extensions();
+        if ext.get::<FormattedFields<DefaultFields>>().is_some() {
+            self.stored.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl PerfBackend for RecordingBackend {
+    fn now(&self) -> Option<f64> {
+        let mut clock = self.clock.lock().unwrap();
+        *clock += 1.0;
+        Some(*clock)
+    }
+    fn mark(&self, name: &str, detail: Option<&JsValue>) -> Result<(), JsValue> {
+        self.record_detail(detail);
+        self.record(format!("mark {name}"))
+    }
+    fn measure(
+        &self,
+        name: &str,
+        start: &str,
+        end: Option<&str>,
+        detail: Option<&JsValue>,
+    ) -> Result<(), JsValue> {
+        self.record_detail(detail);
+        self.record(format!(
+            "measure {name} from {start} to {}",
+            end.unwrap_or("now")
+        ))
+    }
+    fn measure_between(
+        &self,
+        name: &str,
+        start: f64,
+        end: f64,
+        detail: Option<&JsValue>,
+    ) -> Result<(), JsValue> {
+        self.record_detail(detail);
+        self.record(format!("measure {name} from {start} to {end}"))
+    }
+    fn clear(&self, prefix: &str) {
+        self.entries.lock().unwrap().retain(|entry| {
+            !entry
+                .split_once(' ')
+                .is_some_and(|(_, name)| name.starts_with(prefix))
+        });
+    }
+}
+
+#[wasm_bindgen_test]
+fn enter_and_exit_emit_marks_and_measure() {
+    let backend = RecordingBackend::default();
+    let layer = performance_layer()
+        .with_name_key("key")
+        .with_backend(backend.clone());
+    let subscriber = tracing_subscriber::registry().with(layer);
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::info_span!("work", key = "a").in_scope(|| {});
+    });
+    assert_eq!(
+        backend.entries(),
+        [
+            "mark work [a]: span-enter",
+            "mark work [a]: span-exit",
+            "measure work [a]: span-measure from work [a]: span-enter to work [a]: span-exit",
+        ]
+    );
+}
+
+#[wasm_bindgen_test]
+fn timestamp_measures_skip_marks() {
+    let backend = RecordingBackend::default();
+    let layer = performance_layer()
+        .with_name_key("key")
+        .with_namespace("app")
+        .with_timestamp_measures()
+        .with_backend(backend.clone());
+    let subscriber = tracing_subscriber::registry().with(layer);
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::info_span!("work", key = "a").in_scope(|| {});
+    });
+    assert_eq!(
+        backend.entries(),
+        ["measure app:work [a]: span-measure from 1 to 2"]
+    );
+}
+
+#[wasm_bindgen_test]
+fn without_details_skips_fields() {
+    let backend = RecordingBackend::default();
+    let visits = Arc::new(AtomicUsize::new(0));
+    let layer = performance_layer()
+        .with_details_from_fields(DefaultFields::new())
+        .without_details()
+        .with_backend(backend.clone());
+    let probe = ExtensionProbe::default();
+    let subscriber = tracing_subscriber::registry()
+        .with(layer)
+        .with(probe.clone());
+    tracing::subscriber::with_default(subscriber, || {
+        let span = tracing::info_span!("work", value = ?Counted(visits.clone()));
+        span.record("value", tracing::field::debug(Counted(visits.clone())));
+        span.in_scope(|| {});
+    });
+    assert_eq!(visits.load(Ordering::Relaxed), 0);
+    assert!(backend.details().is_empty());
+    assert_eq!(backend.entries().len(), 3);
+    assert_eq!(probe.stored(), 0);
+}
+
+// Whether a span created with the layer is reported as measured by `is_perf_sampled`.
+fn reports_measured(layer: PerformanceEventsLayer<Registry>) -> bool {
+    let subscriber = tracing_subscriber::registry().with(layer);
+    tracing::subscriber::with_default(subscriber, || {
+        let span = tracing::info_span!("work");
+        span.id().is_some_and(|id| is_perf_sampled(&id))
+    })
+}
+
+#[wasm_bindgen_test]
+fn is_perf_sampled_reports_measured_spans() {
+    let backend = RecordingBackend::default();
+    assert!(reports_measured(
+        performance_layer().with_backend(backend.clone())
+    ));
+    assert!(reports_measured(
+        performance_layer()
+            .with_sampling(1.0)
+            .with_backend(backend.clone())
+    ));
+    assert!(!reports_measured(
+        performance_layer()
+            .with_sampling(0.0)
+            .with_backend(backend.clone())
+    ));
+}
+
+#[wasm_bindgen_test]
+fn web_trace_layer_records_follows_from() {
+    let backend = RecordingBackend::default();
+    let layer = web_trace_layer()
+        .map_performance(|layer| layer.with_follows_from(true).with_backend(backend.clone()));
+    let subscriber = tracing_subscriber::registry().with(layer);
+    tracing::subscriber::with_default(subscriber, || {
+        let cause = tracing::info_span!("cause");
+        let effect = tracing::info_span!("effect");
+        effect.follows_from(&cause);
+        effect.in_scope(|| {});
+    });
+    assert!(backend
+        .details()
+        .iter()
+        .any(|detail| detail.contains(r#""follows":[{"#) && detail.contains(r#""name":"cause""#)));
+}
+
+#[wasm_bindgen_test]
+fn builder_configures_layer() {
+    let backend = RecordingBackend::default();
+    let layer = PerformanceLayerBuilder::new()
+        .with_name_key("key")
+        .with_namespace("app")
+        .with_timestamp_measures()
+        .with_backend(backend.clone())
+        .build();
+    let subscriber = tracing_subscriber::registry().with(layer);
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::info_span!("work", key = "a").in_scope(|| {});
+    });
+    assert_eq!(
+        backend.entries(),
+        ["measure app:work [a]: span-measure from 1 to 2"]
+    );
+}
+
+#[wasm_bindgen_test]
+fn marks_do_not_turn_off_timestamp_measures() {
+    let backend = RecordingBackend::default();
+    let layer = performance_layer()
+        .with_name_key("key")
+        .with_timestamp_measures()
+        .with_marks(true)
+        .with_backend(backend.clone());
+    let subscriber = tracing_subscriber::registry().with(layer);
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::info_span!("work", key = "a").in_scope(|| {});
+    });
+    assert_eq!(
+        backend.entries(),
+        ["measure work [a]: span-measure from 1 to 2"]
+    );
+}
+
+#[wasm_bindgen_test]
+fn clear_requires_namespace() {
+    let backend = RecordingBackend::default();
+    let layer: PerformanceEventsLayer<Registry> = performance_layer().with_backend(backend.clone());
+    backend.mark("other", None).unwrap();
+    assert!(!layer.clear());
+    assert_eq!(backend.entries(), ["mark other"]);
+
+    let layer: PerformanceEventsLayer<Registry> = performance_layer()
+        .with_namespace("app")
+        .with_backend(backend.clone());
+    backend.mark("app:work", None).unwrap();
+    assert!(layer.clear());
+    assert_eq!(backend.entries(), ["mark other"]);
+}
+
+#[wasm_bindgen_test]
+fn open_spans_track_coalesced_spans() {
+    let backend = RecordingBackend::default();
+    let layer = performance_layer()
+        .with_name_key("key")
+        .with_coalesced_measures()
+        .with_open_span_tracking()
+        .with_backend(backend.clone());
+    let open_spans = layer.open_spans().unwrap();
+    let subscriber = tracing_subscriber::registry().with(layer);
+    tracing::subscriber::with_default(subscriber, || {
+        let span = tracing::info_span!("work", key = "a");
+        // Exited in between, like the span of a pending future
+        span.in_scope(|| {});
+        open_spans.flush();
+        assert_eq!(
+            backend.entries(),
+            ["measure work [a]: span-measure from 1 to 2"]
+        );
+    });
+}
+
+// The sampling decisions for a batch of spans that are alive at the same time, with fresh ids.
+fn sampling_decisions(seed: u64) -> Vec<bool> {
+    let layer = performance_layer()
+        .with_sampling(0.5)
+        .with_sampling_seed(seed)
+        .with_backend(RecordingBackend::default());
+    let subscriber = tracing_subscriber::registry().with(layer);
+    tracing::subscriber::with_default(subscriber, || {
+        let spans: Vec<_> = (0..64).map(|_| tracing::info_span!("work")).collect();
+        spans
+            .iter()
+            .map(|span| span.id().is_some_and(|id| is_perf_sampled(&id)))
+            .collect()
+    })
+}
+
+#[wasm_bindgen_test]
+fn sampling_is_deterministic_per_seed() {
+    let decisions = sampling_decisions(7);
+    assert_eq!(decisions, sampling_decisions(7));
+    assert!(decisions.contains(&true) && decisions.contains(&false));
+    assert_ne!(decisions, sampling_decisions(8));
+}